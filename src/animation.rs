@@ -0,0 +1,156 @@
+use crate::args::{MergedConfig, MsgAction};
+use crate::colour::Colour;
+use crate::overlay::FrameOverride;
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+
+/// A short-lived frame effect, advanced on every `wl_surface.frame` callback
+#[derive(Debug, Clone)]
+pub struct Animation {
+    effect: Effect,
+    started: Instant,
+    duration: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum Effect {
+    /// Jump to a colour and fade back
+    Flash { color: String },
+    /// Ease towards a colour and/or opacity and back, `count` times
+    Pulse { color: Option<String>, opacity: Option<f64>, count: u32 },
+    /// Move from a previously drawn config to the current one
+    Transition { from: Box<MergedConfig>, easing: Easing },
+}
+
+/// Timing curve for config reload transitions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('_', "-").as_str() {
+            "linear" => Some(Self::Linear),
+            "ease-in" => Some(Self::EaseIn),
+            "ease-out" => Some(Self::EaseOut),
+            "ease-in-out" | "ease" => Some(Self::EaseInOut),
+            _ => None,
+        }
+    }
+
+    /// Cubic curves mapping linear progress to eased progress
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+impl Animation {
+    pub fn from_action(action: &MsgAction) -> Option<Self> {
+        let (effect, duration) = match action {
+            MsgAction::Flash { color, duration } => (Effect::Flash { color: color.clone() }, *duration),
+            MsgAction::Pulse { color, opacity, duration, count } => (
+                Effect::Pulse {
+                    color: color.clone(),
+                    opacity: *opacity,
+                    count: (*count).max(1),
+                },
+                *duration,
+            ),
+            MsgAction::Profile { .. } => return None,
+        };
+        Some(Self {
+            effect,
+            started: Instant::now(),
+            duration,
+        })
+    }
+
+    /// Animate from `from` to whatever the config is now
+    pub fn transition(from: MergedConfig, duration: Duration, easing: Easing) -> Self {
+        Self {
+            effect: Effect::Transition { from: Box::new(from), easing },
+            started: Instant::now(),
+            duration,
+        }
+    }
+
+    fn progress(&self, now: Instant) -> f64 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (now.duration_since(self.started).as_secs_f64() / self.duration.as_secs_f64()).clamp(0.0, 1.0)
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// Frame changes at `now`, relative to the otherwise effective config
+    pub fn sample(&self, base: &MergedConfig, now: Instant) -> FrameOverride {
+        let t = self.progress(now);
+        // Colours are validated before they get here, fall back to "no change"
+        let Ok(from) = base.colour() else {
+            return FrameOverride::default();
+        };
+
+        let (to, weight) = match &self.effect {
+            Effect::Transition { from, easing } => {
+                return transition_frame(from, base, easing.apply(t));
+            }
+            // Quadratic ease out, so the flash reads as a sharp hit that settles
+            Effect::Flash { color } => (Colour::parse(color).unwrap_or(from), (1.0 - t).powi(2)),
+            Effect::Pulse { color, opacity, count } => {
+                let to = color
+                    .as_deref()
+                    .and_then(|c| Colour::parse(c).ok())
+                    .unwrap_or(from);
+                let to = opacity.map_or(to, |o| to.with_alpha(o));
+                (to, (PI * *count as f64 * t).sin().powi(2))
+            }
+        };
+
+        colour_frame(from.mix(to, weight))
+    }
+}
+
+/// Override carrying just a colour, with its alpha as the opacity
+fn colour_frame(colour: Colour) -> FrameOverride {
+    FrameOverride {
+        color: Some(colour.to_hex()),
+        opacity: Some(colour.a),
+        ..FrameOverride::default()
+    }
+}
+
+/// Frame part way between two configs
+pub fn transition_frame(from: &MergedConfig, to: &MergedConfig, weight: f64) -> FrameOverride {
+    let lerp = |a: i32, b: i32| (a as f64 + (b - a) as f64 * weight).round() as i32;
+    let (Ok(from_colour), Ok(to_colour)) = (from.colour(), to.colour()) else {
+        return FrameOverride::default();
+    };
+    FrameOverride {
+        radius: Some(lerp(from.radius, to.radius)),
+        borders: Some([
+            lerp(from.top, to.top),
+            lerp(from.right, to.right),
+            lerp(from.bottom, to.bottom),
+            lerp(from.left, to.left),
+        ]),
+        ..colour_frame(from_colour.mix(to_colour, weight))
+    }
+}
@@ -1,15 +1,22 @@
+use crate::animation::Animation;
 use crate::args::MergedConfig;
-use crate::colour::parse_colour;
+use crate::colour::Colour;
 use crate::drawing::draw_snug;
+use crate::overlay::RuntimeOverlay;
+use crate::power::OutputPower;
+use crate::swapchain::Swapchain;
 use smithay_client_toolkit::{
     compositor::{CompositorState, Region},
     output::OutputState,
     registry::RegistryState,
     seat::SeatState,
     shell::{wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerShell, LayerSurface}, WaylandSurface},
-    shm::{slot::SlotPool, Shm},
+    shm::Shm,
 };
 use wayland_client::{protocol::wl_output, QueueHandle};
+use std::time::Instant;
+use tracing::{debug, error, info, trace, warn};
+use crate::solid::SolidFrame;
 
 pub struct App {
     pub registry_state: RegistryState,
@@ -18,113 +25,225 @@ pub struct App {
     pub compositor_state: CompositorState,
     pub layer_shell: LayerShell,
     pub shm: Shm,
-    pub pool: Option<SlotPool>,
+    pub swapchain: Option<Swapchain>,
     pub layer: Option<LayerSurface>,
     pub width: i32,
     pub height: i32,
     pub config: MergedConfig,
+    /// Runtime layers written by integrations, applied on top of `config`
+    pub overlay: RuntimeOverlay,
+    /// Running flash/pulse effect, driven by frame callbacks
+    pub animation: Option<Animation>,
     
     // Track which output we're bound to
     pub bound_output: Option<wl_output::WlOutput>,
     pub target_display_name: String,
     pub needs_recreation: bool,
+    /// Whether the bound output is powered, nothing is drawn while it is off
+    pub power: OutputPower,
+    /// Draws square frames without SHM when the compositor allows it
+    pub solid: SolidFrame,
+    /// What the surface shows, so unchanged frames aren't drawn again
+    pub drawn: Option<DrawnFrame>,
+    /// A draw found both buffers busy and waits for one to be released
+    pub draw_pending: bool,
+}
+
+/// Everything a committed frame was rendered from
+#[derive(Debug, PartialEq)]
+pub struct DrawnFrame {
+    config: MergedConfig,
+    hidden: bool,
+    size: (i32, i32),
 }
 
 impl App {
+    /// Render and commit the frame, unless the surface already shows it
     pub fn draw(&mut self) {
-        let Some(pool) = self.pool.as_mut() else { 
-            eprintln!("[{}] draw() called but pool is None", self.target_display_name);
+        self.draw_pending = false;
+        if !self.power.is_on() {
+            trace!("Output is off, not drawing");
+            return;
+        }
+        let config = self.effective_config();
+        let hidden = self.overlay.hidden();
+        let frame = DrawnFrame {
+            config: config.clone(),
+            hidden,
+            size: (self.width, self.height),
+        };
+        if self.drawn.as_ref() == Some(&frame) {
+            trace!("Frame unchanged, not drawing");
+            return;
+        }
+        
+        let Some(swapchain) = self.swapchain.as_mut() else { 
+            warn!("draw() called but swapchain is None");
             return;
         };
         let Some(layer) = &self.layer else {
-            eprintln!("[{}] draw() called but layer is None", self.target_display_name); 
+            warn!("draw() called but layer is None");
             return;
         };
         if self.width == 0 || self.height == 0 {
-            eprintln!("[{}] draw() called but dimensions are zero: {}x{}", 
-                     self.target_display_name, self.width, self.height);
+            debug!("draw() called but dimensions are zero: {}x{}", self.width, self.height);
             return;
         }
         
-        eprintln!("[{}] Drawing with dimensions {}x{}", self.target_display_name, self.width, self.height);
+        trace!("Drawing with dimensions {}x{}", self.width, self.height);
         
-        let stride = self.width * 4;
-        let (buffer, canvas) = match pool.create_buffer(
-            self.width, 
-            self.height, 
-            stride, 
-            wayland_client::protocol::wl_shm::Format::Argb8888
-        ) {
-            Ok(b) => b,
-            Err(e) => {
-                eprintln!("[{}] Failed to create buffer: {:?}", self.target_display_name, e);
-                return;
+        let colour = config.colour().unwrap_or_else(|e| {
+            warn!("{}, drawing black", e);
+            Colour::BLACK
+        });
+        let surface = layer.wl_surface();
+        
+        // Square frames don't need pixels of their own
+        let buffer = if self.solid.fits(&config) {
+            None
+        } else {
+            match swapchain.acquire(self.width, self.height) {
+                Ok(Some((buffer, canvas))) => {
+                    if hidden {
+                        canvas.fill(0);
+                    } else {
+                        draw_snug(canvas, self.width, self.height, colour, &config);
+                    }
+                    Some(buffer)
+                }
+                Ok(None) => {
+                    trace!("Both buffers still in use, drawing once one is released");
+                    self.draw_pending = true;
+                    return;
+                }
+                Err(e) => {
+                    error!("Failed to create buffer: {:?}", e);
+                    return;
+                }
             }
         };
         
-        let (r, g, b, a) = parse_colour(&self.config.color, self.config.opacity);
-        draw_snug(canvas, self.width, self.height, r, g, b, a, &self.config);
-        
-        let surface = layer.wl_surface();
-        
         // Set input region to only the border areas
         let region = match Region::new(&self.compositor_state) {
             Ok(r) => r,
             Err(e) => {
-                eprintln!("[{}] Failed to create region: {:?}", self.target_display_name, e);
+                error!("Failed to create region: {:?}", e);
                 return;
             }
         };
         
-        let left = self.config.left;
-        let right = self.config.right;
-        let top = self.config.top;
-        let bottom = self.config.bottom;
-        
-        // Top border
-        region.add(0, 0, self.width, top);
-        // Bottom border
-        region.add(0, self.height - bottom, self.width, bottom);
-        // Left border (excluding corners already covered)
-        region.add(0, top, left, self.height - top - bottom);
-        // Right border (excluding corners already covered)
-        region.add(self.width - right, top, right, self.height - top - bottom);
+        // Let all input through while hidden
+        if !hidden {
+            let left = config.left;
+            let right = config.right;
+            let top = config.top;
+            let bottom = config.bottom;
+            
+            // Top border
+            region.add(0, 0, self.width, top);
+            // Bottom border
+            region.add(0, self.height - bottom, self.width, bottom);
+            // Left border (excluding corners already covered)
+            region.add(0, top, left, self.height - top - bottom);
+            // Right border (excluding corners already covered)
+            region.add(self.width - right, top, right, self.height - top - bottom);
+        }
         
         surface.set_input_region(Some(region.wl_region()));
         
-        surface.attach(Some(buffer.wl_buffer()), 0, 0);
-        surface.damage_buffer(0, 0, self.width, self.height);
-        surface.commit();
+        match buffer {
+            Some(buffer) => {
+                // Marks the buffer busy until the compositor releases it
+                if let Err(e) = buffer.attach_to(surface) {
+                    error!("Failed to attach buffer: {}", e);
+                    return;
+                }
+                self.solid.hide();
+                surface.damage_buffer(0, 0, self.width, self.height);
+                surface.commit();
+                trace!("Draw complete - buffer attached and committed");
+            }
+            None => {
+                self.solid.draw(surface, self.width, self.height, &config, colour, hidden);
+                trace!("Draw complete - single-pixel edges committed");
+            }
+        }
+        
+        self.drawn = Some(frame);
+    }
+    
+    /// Forget what the surface shows, so the next draw commits even if nothing changed
+    ///
+    /// Needed whenever the compositor expects a commit: after a configure, for
+    /// a new surface, or for the next frame callback.
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+    
+    /// The config as it should look right now: merged config, runtime
+    /// overlay, then any running animation
+    pub fn effective_config(&self) -> MergedConfig {
+        let mut config = self.overlay.apply(&self.config);
+        if let Some(animation) = &self.animation {
+            animation.sample(&config, Instant::now()).apply(&mut config);
+        }
+        config
+    }
+    
+    /// Start an animation, replacing any that is still running
+    pub fn start_animation(&mut self, qh: &QueueHandle<Self>, animation: Animation) {
+        self.animation = Some(animation);
+        self.animate(qh);
+    }
+    
+    /// Draw the next animation frame and ask for a callback for the one after
+    pub fn animate(&mut self, qh: &QueueHandle<Self>) {
+        let Some(animation) = &self.animation else { return };
+        
+        if animation.is_finished(Instant::now()) {
+            // Final draw without the effect, then stop requesting frames
+            self.animation = None;
+        } else if let Some(layer) = &self.layer {
+            let surface = layer.wl_surface();
+            surface.frame(qh, surface.clone());
+            // The callback only comes after a commit, even if this frame looks like the last
+            self.invalidate();
+        }
         
-        eprintln!("[{}] Draw complete - buffer attached and committed", self.target_display_name);
+        if self.width > 0 && self.height > 0 {
+            self.draw();
+        }
     }
     
     pub fn recreate_layer_surface(&mut self, qh: &QueueHandle<Self>, output: Option<wl_output::WlOutput>) {
-        eprintln!("[{}] Recreating layer surface...", self.target_display_name);
+        info!("Recreating layer surface...");
         
         // Destroy old layer surface if it exists
         if let Some(old_layer) = self.layer.take() {
-            eprintln!("[{}] Dropping old layer surface", self.target_display_name);
+            debug!("Dropping old layer surface");
+            self.solid.forget_surface();
             drop(old_layer);
         }
         
-        // CRITICAL: Recreate the buffer pool too!
-        // The old pool might be tied to the old surface or invalid after DPMS
-        eprintln!("[{}] Creating new buffer pool", self.target_display_name);
-        match SlotPool::new(256 * 256 * 4, &self.shm) {
-            Ok(new_pool) => {
-                self.pool = Some(new_pool);
-                eprintln!("[{}] New buffer pool created successfully", self.target_display_name);
+        // Keep the pool, but not buffers the old surface may still hold
+        match &mut self.swapchain {
+            Some(swapchain) => swapchain.reset(),
+            None => match Swapchain::new(&self.shm) {
+                Ok(swapchain) => {
+                    self.swapchain = Some(swapchain);
+                    debug!("New buffer pool created successfully");
+                }
+                Err(e) => {
+                    error!("Failed to create new buffer pool: {:?}", e);
+                    return;
+                }
             },
-            Err(e) => {
-                eprintln!("[{}] Failed to create new buffer pool: {:?}", self.target_display_name, e);
-                return;
-            }
         }
+        self.invalidate();
         
         // Create new surface
         let surface = self.compositor_state.create_surface(qh);
-        eprintln!("[{}] New wl_surface created", self.target_display_name);
+        debug!("New wl_surface created");
         
         // Create new layer surface bound to the output
         let layer = self.layer_shell.create_layer_surface(
@@ -134,7 +253,7 @@ impl App {
             Some("snug-overlay"),
             output.as_ref(),
         );
-        eprintln!("[{}] New layer surface created", self.target_display_name);
+        debug!("New layer surface created");
         
         // Configure the layer surface with explicit settings
         layer.set_anchor(Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT);
@@ -142,17 +261,18 @@ impl App {
         layer.set_exclusive_zone(-1);
         layer.set_keyboard_interactivity(KeyboardInteractivity::None);
         layer.commit();
-        eprintln!("[{}] Layer surface configured and committed", self.target_display_name);
+        debug!("Layer surface configured and committed");
         
         // Store the new layer and output reference
         self.layer = Some(layer);
         self.bound_output = output;
+        self.power.watch(self.bound_output.as_ref(), qh);
         self.needs_recreation = false;
         
         // Reset dimensions - will be set by configure event
         self.width = 0;
         self.height = 0;
         
-        eprintln!("[{}] Recreation complete, waiting for configure event", self.target_display_name);
+        debug!("Recreation complete, waiting for configure event");
     }
 }
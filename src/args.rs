@@ -1,16 +1,44 @@
-use clap::Parser;
+use crate::colour::{Colour, ColourError};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Debug, Clone, Default)]
 #[command(author, version, about = "Rounded corner border overlay for Wayland")]
 pub struct Args {
-    /// Specify a custom configuration file
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    
+    /// Specify a custom configuration file (overrides $SNUG_CONFIG)
     #[arg(short, long)]
-    pub config: Option<String>,
+    pub config: Option<PathBuf>,
     
     /// Target display name (e.g., DP-1, HDMI-A-1)
     #[arg(short, long)]
     pub display: Option<String>,
     
+    /// Use the display blocks of this profile (overrides $SNUG_PROFILE)
+    #[arg(long)]
+    pub profile: Option<String>,
+    
+    /// Log more, repeat for trace output (-vv); $SNUG_LOG takes precedence
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    
+    /// Log less, repeat for errors only (-qq)
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "verbose")]
+    pub quiet: u8,
+    
+    /// Append logs to this file instead of stderr
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+    
+    /// Pause drawing while outputs are off; holds each output's power control,
+    /// so wlopm or swayidle can no longer switch it
+    #[arg(long)]
+    pub track_power: bool,
+    
     /// Corner radius in pixels (overrides config)
     #[arg(short, long)]
     pub radius: Option<i32>,
@@ -31,8 +59,8 @@ pub struct Args {
     #[arg(long)]
     pub bottom: Option<i32>,
     
-    /// Color in hex format (RGB: 000000 or RGBA: 000000ff) (overrides config)
-    #[arg(long)]
+    /// Color as hex (000000, 000000ff), rgb(), hsl(), oklch() or a CSS name (overrides config)
+    #[arg(long, value_parser = parse_colour_arg)]
     pub color: Option<String>,
     
     /// Opacity (0.0 to 1.0) - overrides alpha channel if present in color
@@ -43,8 +71,8 @@ pub struct Args {
     #[arg(long)]
     pub shadow_enabled: Option<bool>,
     
-    /// Shadow color in hex format (overrides config)
-    #[arg(long)]
+    /// Shadow color, same formats as --color (overrides config)
+    #[arg(long, value_parser = parse_colour_arg)]
     pub shadow_color: Option<String>,
     
     /// Shadow opacity (0.0 to 1.0) (overrides config)
@@ -54,6 +82,126 @@ pub struct Args {
     /// Shadow blur radius (overrides config)
     #[arg(long)]
     pub shadow_blur: Option<f64>,
+    
+    /// Colour space for anti-aliased edges and shadow: linear or srgb (overrides config)
+    #[arg(long, value_parser = ["linear", "srgb"])]
+    pub blend: Option<String>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Send a message to running snug instances
+    Msg {
+        /// Only message the instance for this display
+        #[arg(short, long)]
+        display: Option<String>,
+        
+        #[command(subcommand)]
+        action: MsgAction,
+    },
+    /// Check a config file and report every problem found
+    Check {
+        /// Config file to check (defaults to the one snug would load)
+        path: Option<PathBuf>,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum ConfigAction {
+    /// Print the config file snug would load and where that choice came from
+    Path,
+    /// Print the merged config for each display, noting where every value came from
+    Dump {
+        /// Only show this display
+        #[arg(short, long)]
+        display: Option<String>,
+        
+        /// Output format
+        #[arg(long, value_enum, default_value_t = DumpFormat::Rune)]
+        format: DumpFormat,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum DumpFormat {
+    Rune,
+    Json,
+    Toml,
+}
+
+/// Sent to running instances as one JSON line, so values may contain spaces
+#[derive(Subcommand, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum MsgAction {
+    /// Switch the frame to a colour, then fade back
+    Flash {
+        /// Colour to flash, same formats as --color
+        #[arg(long, default_value = "ffffff", value_parser = parse_colour_arg)]
+        color: String,
+        
+        /// How long the fade back takes (e.g. 400ms, 1.5s)
+        #[arg(long, default_value = "400ms", value_parser = parse_duration)]
+        duration: Duration,
+    },
+    /// Smoothly pulse the frame colour and/or opacity, then revert
+    Pulse {
+        /// Colour to pulse towards
+        #[arg(long, value_parser = parse_colour_arg)]
+        color: Option<String>,
+        
+        /// Opacity to pulse towards (0.0 to 1.0)
+        #[arg(long)]
+        opacity: Option<f64>,
+        
+        /// Total length of the animation (e.g. 400ms, 1.5s)
+        #[arg(long, default_value = "1s", value_parser = parse_duration)]
+        duration: Duration,
+        
+        /// Number of pulses within the duration
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+    },
+    /// Switch to a profile from the config
+    Profile {
+        /// Profile to switch to; leave out to go back to the base config
+        name: Option<String>,
+    },
+}
+
+/// Validate a colour argument up front so typos fail at the command line
+fn parse_colour_arg(value: &str) -> Result<String, String> {
+    Colour::parse(value)
+        .map(|_| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Parse durations such as `400ms`, `1.5s`, `30m`, `2h` or a bare number of milliseconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = value.strip_suffix('s') {
+        (s, 1.0)
+    } else if let Some(m) = value.strip_suffix('m') {
+        (m, 60.0)
+    } else if let Some(h) = value.strip_suffix('h') {
+        (h, 3600.0)
+    } else {
+        (value, 0.001)
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration '{}', expected e.g. 400ms, 1.5s or 30m", value))?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(format!("invalid duration '{}'", value));
+    }
+    Ok(Duration::from_secs_f64(number * scale))
 }
 
 impl Args {
@@ -71,11 +219,12 @@ impl Args {
             shadow_color: self.shadow_color.clone().or_else(|| config.shadow_color.clone()),
             shadow_opacity: self.shadow_opacity.or(config.shadow_opacity),
             shadow_blur: self.shadow_blur.or(config.shadow_blur),
+            blend: self.blend.clone().or_else(|| config.blend.clone()),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MergedConfig {
     pub radius: i32,
     pub left: i32,
@@ -88,4 +237,12 @@ pub struct MergedConfig {
     pub shadow_color: Option<String>,
     pub shadow_opacity: Option<f64>,
     pub shadow_blur: Option<f64>,
+    pub blend: Option<String>,
+}
+
+impl MergedConfig {
+    /// Frame colour with the opacity override applied
+    pub fn colour(&self) -> Result<Colour, ColourError> {
+        Ok(Colour::parse(&self.color)?.with_opacity(self.opacity))
+    }
 }
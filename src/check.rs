@@ -0,0 +1,754 @@
+use crate::animation::Easing;
+use crate::args::parse_duration;
+use crate::colour::Colour;
+use crate::config::{split_colour_entry, KNOWN_DISPLAYS};
+use crate::config_set::ConfigSet;
+use crate::config_source::ConfigSource;
+use crate::drawing::BlendSpace;
+use crate::indicator::{self, Source};
+use crate::palette::Palette;
+use crate::paths::locate_config;
+use crate::schedule::Schedule;
+use crate::wayland;
+use rune_cfg::{RuneError, Value};
+use std::path::{Path, PathBuf};
+
+/// What a display block key holds, and so how it is validated
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Non-negative whole number of pixels
+    Pixels,
+    /// Number between 0 and 1
+    Unit,
+    Bool,
+    Colour,
+    /// `["name:colour", ...]`
+    ColourList,
+    Duration,
+    /// Duration with a unit, such as `"5s"`
+    Interval,
+    Easing,
+    Blend,
+    /// `battery` or `thermal`
+    Indicator,
+    /// `["threshold:colour", ...]`
+    Thresholds,
+    /// Any string, such as a path or device name
+    Text,
+}
+
+const DISPLAY_KEYS: &[(&str, Kind)] = &[
+    ("radius", Kind::Pixels),
+    ("left", Kind::Pixels),
+    ("right", Kind::Pixels),
+    ("top", Kind::Pixels),
+    ("bottom", Kind::Pixels),
+    ("color", Kind::Colour),
+    ("opacity", Kind::Unit),
+    ("shadow_enabled", Kind::Bool),
+    ("shadow_color", Kind::Colour),
+    ("shadow_opacity", Kind::Unit),
+    ("shadow_blur", Kind::Unit),
+    ("blend", Kind::Blend),
+    ("workspace_colors", Kind::ColourList),
+    ("hide_on_fullscreen", Kind::Bool),
+    ("overview_opacity", Kind::Unit),
+    ("mode_colors", Kind::ColourList),
+    ("urgent_color", Kind::Colour),
+    ("transition_duration", Kind::Duration),
+    ("transition_easing", Kind::Easing),
+    ("indicator", Kind::Indicator),
+    ("indicator_colors", Kind::Thresholds),
+    ("indicator_charging_color", Kind::Colour),
+    ("indicator_device", Kind::Text),
+    ("source", Kind::Text),
+];
+
+/// Integration and indicator blocks and their keys
+const SECTION_KEYS: &[(&str, &[(&str, Kind)])] = &[
+    ("hyprland", &[("enabled", Kind::Bool), ("sync_gaps", Kind::Bool)]),
+    ("niri", &[("enabled", Kind::Bool)]),
+    ("sway", &[("enabled", Kind::Bool)]),
+    ("indicator", &[("sysfs_root", Kind::Text), ("interval", Kind::Interval)]),
+];
+
+/// Keys of the `schedule:` block
+const SCHEDULE_KEYS: &[&str] = &["times", "latitude", "longitude", "fade"];
+
+#[derive(Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+struct Diagnostic {
+    severity: Severity,
+    /// File the problem was found in
+    file: PathBuf,
+    /// Dotted config path, used to find the line
+    key: String,
+    message: String,
+    help: Option<String>,
+}
+
+#[derive(Default)]
+struct Report {
+    /// File being checked, recorded on every diagnostic
+    file: PathBuf,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    fn error(&mut self, key: &str, message: impl Into<String>) -> &mut Diagnostic {
+        self.push(Severity::Error, key, message.into())
+    }
+
+    fn warning(&mut self, key: &str, message: impl Into<String>) -> &mut Diagnostic {
+        self.push(Severity::Warning, key, message.into())
+    }
+
+    fn push(&mut self, severity: Severity, key: &str, message: String) -> &mut Diagnostic {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: self.file.clone(),
+            key: key.to_string(),
+            message,
+            help: None,
+        });
+        self.diagnostics.last_mut().unwrap()
+    }
+}
+
+impl Diagnostic {
+    fn help(&mut self, help: impl Into<String>) {
+        self.help = Some(help.into());
+    }
+}
+
+/// Check a config file and everything it includes, print every problem and
+/// return whether it is free of errors
+pub fn run(path: Option<&Path>) -> Result<bool, Box<dyn std::error::Error>> {
+    let path = locate_config(path).path;
+
+    let set = match ConfigSet::load(&path) {
+        Ok(set) => set,
+        Err(e) => {
+            println!("error: {}", e);
+            return Ok(false);
+        }
+    };
+
+    let mut report = Report::default();
+    check_config(&set, &mut report);
+
+    for diagnostic in &report.diagnostics {
+        print_diagnostic(set.line_of(&diagnostic.file, &diagnostic.key), diagnostic);
+    }
+
+    let errors = report.diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = report.diagnostics.len() - errors;
+    let checked: Vec<String> = set.files().map(|(file, _)| file.display().to_string()).collect();
+    if report.diagnostics.is_empty() {
+        println!("{}: ok", checked.join(", "));
+    } else {
+        println!("{}: {} error(s), {} warning(s)", checked.join(", "), errors, warnings);
+    }
+    Ok(errors == 0)
+}
+
+fn check_config(set: &ConfigSet, report: &mut Report) {
+    let mut palette = check_palette(set, report);
+    // Display blocks may be split across files, the merged checks run once per display
+    let mut displays: Vec<(String, PathBuf)> = Vec::new();
+
+    for (file, config) in set.files() {
+        report.file = file.to_path_buf();
+        for (name, value) in config.items() {
+            let Value::Object(entries) = value else {
+                report
+                    .warning(name, format!("unknown top-level key '{}'", name))
+                    .help("snug only reads display blocks, `palette:`, `profiles:`, `schedule:`, the integration blocks and `include`");
+                continue;
+            };
+            let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+
+            if KNOWN_DISPLAYS.contains(&name.as_str()) {
+                check_display_keys(set, config, name, &keys, &mut palette, report);
+                if !displays.iter().any(|(display, _)| display == name) {
+                    displays.push((name.clone(), file.to_path_buf()));
+                }
+            } else if let Some((_, known)) = SECTION_KEYS.iter().find(|(section, _)| section == name) {
+                check_section(set, config, name, &keys, known, &mut palette, report);
+            } else if name == "profiles" {
+                check_profiles(set, config, entries, &mut palette, report);
+            } else if name == "schedule" {
+                check_schedule_keys(&keys, report);
+            } else if name != "palette" {
+                let diagnostic = report.warning(name, format!("'{}' is not a display snug knows, so this block is ignored", name));
+                let candidates = KNOWN_DISPLAYS
+                    .iter()
+                    .copied()
+                    .chain(SECTION_KEYS.iter().map(|(section, _)| *section))
+                    .chain(["palette", "profiles", "schedule"]);
+                if let Some(suggestion) = closest(name, candidates) {
+                    diagnostic.help(format!("did you mean '{}'?", suggestion));
+                }
+            }
+        }
+    }
+
+    check_schedule(set, report);
+
+    if displays.is_empty() {
+        return;
+    }
+    let outputs = wayland::output_sizes();
+    for (display, first_file) in displays {
+        let size = outputs.as_ref().and_then(|o| o.get(&display)).copied();
+        check_display_geometry(set, &display, &first_file, size, report);
+    }
+}
+
+/// Every profile holds display blocks, checked like the top-level ones
+fn check_profiles(
+    set: &ConfigSet,
+    config: &dyn ConfigSource,
+    profiles: &[(String, Value)],
+    palette: &mut Palette,
+    report: &mut Report,
+) {
+    for (profile, value) in profiles {
+        let path = format!("profiles.{}", profile);
+        let Value::Object(displays) = value else {
+            report
+                .error(&path, format!("expected a block of display blocks, got {}", describe(value)))
+                .help(format!("write `{}:` followed by display blocks and `end`", profile));
+            continue;
+        };
+        for (display, block) in displays {
+            let path = format!("{}.{}", path, display);
+            if !KNOWN_DISPLAYS.contains(&display.as_str()) {
+                let diagnostic = report.warning(&path, format!("'{}' is not a display snug knows, so this block is ignored", display));
+                if let Some(suggestion) = closest(display, KNOWN_DISPLAYS.iter().copied()) {
+                    diagnostic.help(format!("did you mean '{}'?", suggestion));
+                }
+                continue;
+            }
+            let Value::Object(entries) = block else {
+                report.error(&path, format!("expected a display block, got {}", describe(block)));
+                continue;
+            };
+            let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+            check_display_keys(set, config, &path, &keys, palette, report);
+        }
+    }
+}
+
+fn check_schedule_keys(keys: &[&str], report: &mut Report) {
+    for key in keys {
+        if SCHEDULE_KEYS.contains(key) {
+            continue;
+        }
+        let diagnostic = report.warning(&format!("schedule.{}", key), format!("unknown key '{}'", key));
+        if let Some(suggestion) = closest(key, SCHEDULE_KEYS.iter().copied()) {
+            diagnostic.help(format!("did you mean '{}'?", suggestion));
+        }
+    }
+}
+
+/// The merged schedule parses and only names profiles that exist
+fn check_schedule(set: &ConfigSet, report: &mut Report) {
+    let at = |report: &mut Report, key: &str| {
+        report.file = set.source_of(key).map(|(file, _)| file.to_path_buf()).unwrap_or_default();
+    };
+    match Schedule::from_config(set) {
+        Ok(Some(schedule)) => {
+            for profile in schedule.profiles() {
+                if set.get_keys(&format!("profiles.{}", profile)).is_err() {
+                    at(report, "schedule.times");
+                    report
+                        .error("schedule.times", format!("unknown profile '{}'", profile))
+                        .help("define it in the `profiles:` block, or leave the name out to use the base blocks");
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            let message = e.to_string();
+            let (key, message) = message.split_once(": ").unwrap_or(("schedule", &message));
+            at(report, key);
+            report.error(key, message);
+        }
+    }
+}
+
+/// Value of `path` as written in `config`, following references into other files
+fn file_value(set: &ConfigSet, config: &dyn ConfigSource, path: &str) -> Result<Value, RuneError> {
+    match config.get_value(path)? {
+        Value::Reference(parts) => set.get_value(&parts.join(".")),
+        value => Ok(value),
+    }
+}
+
+fn check_palette(set: &ConfigSet, report: &mut Report) -> Palette {
+    let mut palette = Palette::default();
+    let Ok(names) = set.get_keys("palette") else {
+        return palette;
+    };
+    for name in names {
+        let key = format!("palette.{}", name);
+        report.file = set.source_of(&key).map(|(file, _)| file.to_path_buf()).unwrap_or_default();
+        match set.get_value(&key) {
+            Ok(Value::String(value)) => {
+                if let Err(e) = palette.define(&name, &value) {
+                    report.error(&key, e);
+                }
+            }
+            Ok(other) => {
+                report.error(&key, format!("expected a colour string, got {}", describe(&other)));
+            }
+            Err(e) => {
+                report.error(&key, e.to_string());
+            }
+        }
+    }
+    palette
+}
+
+fn check_display_keys(
+    set: &ConfigSet,
+    config: &dyn ConfigSource,
+    display: &str,
+    keys: &[&str],
+    palette: &mut Palette,
+    report: &mut Report,
+) {
+    for key in keys {
+        let path = format!("{}.{}", display, key);
+        let Some((_, kind)) = DISPLAY_KEYS.iter().find(|(name, _)| name == key) else {
+            let diagnostic = report.warning(&path, format!("unknown key '{}'", key));
+            if let Some(suggestion) = closest(key, DISPLAY_KEYS.iter().map(|(name, _)| *name)) {
+                diagnostic.help(format!("did you mean '{}'?", suggestion));
+            }
+            continue;
+        };
+        match file_value(set, config, &path) {
+            Ok(value) => check_value(&path, *kind, &value, palette, report),
+            Err(e) => {
+                report.error(&path, e.to_string());
+            }
+        }
+    }
+}
+
+/// Checks on the merged display block: it is loaded at all, and fits the output
+fn check_display_geometry(
+    set: &ConfigSet,
+    display: &str,
+    first_file: &Path,
+    output_size: Option<(i32, i32)>,
+    report: &mut Report,
+) {
+    let radius_key = format!("{}.radius", display);
+    if set.get_value(&radius_key).is_err() {
+        report.file = first_file.to_path_buf();
+        report
+            .error(display, format!("'{}' has no radius, so snug ignores the whole block", display))
+            .help("add `radius = 15` (or 0 for square corners)");
+    }
+
+    let pixels = |key: &str, default: i64| match set.get_value(&format!("{}.{}", display, key)) {
+        Ok(Value::Number(n)) => n as i64,
+        _ => default,
+    };
+    let (radius, left, right, top, bottom) = (
+        pixels("radius", 15),
+        pixels("left", 30),
+        pixels("right", 30),
+        pixels("top", 30),
+        pixels("bottom", 30),
+    );
+
+    let Some((width, height)) = output_size else {
+        return;
+    };
+    let (width, height) = (width as i64, height as i64);
+    let at = |report: &mut Report, key: &str| {
+        let path = format!("{}.{}", display, key);
+        report.file = set.source_of(&path).map_or_else(|| first_file.to_path_buf(), |(file, _)| file.to_path_buf());
+        path
+    };
+    let left_key = at(report, "left");
+    if left + right >= width {
+        report
+            .error(&left_key, format!("left + right borders ({}px) cover the whole {}px wide output", left + right, width))
+            .help("borders are in logical pixels, after output scaling");
+    }
+    let top_key = at(report, "top");
+    if top + bottom >= height {
+        report
+            .error(&top_key, format!("top + bottom borders ({}px) cover the whole {}px high output", top + bottom, height))
+            .help("borders are in logical pixels, after output scaling");
+    }
+    let radius_key = at(report, "radius");
+    let inner = (width - left - right).min(height - top - bottom);
+    if inner > 0 && radius * 2 > inner {
+        report
+            .warning(&radius_key, format!("radius {}px is more than half the {}px opening", radius, inner))
+            .help(format!("corners will meet; at most {} keeps them round", inner / 2));
+    }
+}
+
+fn check_value(path: &str, kind: Kind, value: &Value, palette: &mut Palette, report: &mut Report) {
+    let colour = |value: &str, palette: &mut Palette| -> Result<(), String> {
+        let resolved = palette.resolve(value)?;
+        Colour::parse(&resolved).map(|_| ()).map_err(|e| e.to_string())
+    };
+
+    match (kind, value) {
+        (Kind::Pixels, Value::Number(n)) => {
+            if n.fract() != 0.0 {
+                report.error(path, format!("expected a whole number of pixels, got {}", n));
+            } else if *n < 0.0 {
+                report.error(path, format!("must not be negative, got {}", n));
+            }
+        }
+        (Kind::Unit, Value::Number(n)) => {
+            if !(0.0..=1.0).contains(n) {
+                report
+                    .error(path, format!("must be between 0.0 and 1.0, got {}", n))
+                    .help(if *n > 1.0 && *n <= 100.0 {
+                        format!("for {}% write {}", n, n / 100.0)
+                    } else {
+                        "0.0 is fully transparent, 1.0 fully opaque".to_string()
+                    });
+            }
+        }
+        (Kind::Bool, Value::Bool(_)) => {}
+        (Kind::Colour, Value::String(value)) => {
+            if let Err(e) = colour(value, palette) {
+                report
+                    .error(path, e)
+                    .help("use hex, rgb(), hsl(), oklch(), a CSS name, a palette name or wal:colorN");
+            }
+        }
+        (Kind::ColourList, Value::Array(entries)) => {
+            for entry in entries {
+                let Value::String(entry) = entry else {
+                    report.error(path, format!("expected \"name:colour\" strings, got {}", describe(entry)));
+                    continue;
+                };
+                let Some((_, value)) = split_colour_entry(entry) else {
+                    report
+                        .error(path, format!("expected \"name:colour\", got '{}'", entry))
+                        .help("e.g. \"1:161b22\" for workspace 1");
+                    continue;
+                };
+                if let Err(e) = colour(value.trim(), palette) {
+                    report.error(path, format!("'{}': {}", entry, e));
+                }
+            }
+        }
+        (Kind::Duration, Value::String(value)) => {
+            if let Err(e) = parse_duration(value) {
+                report.error(path, e);
+            }
+        }
+        (Kind::Interval, Value::String(value)) => {
+            if let Err(e) = indicator::parse_interval(value) {
+                report.error(path, e);
+            }
+        }
+        (Kind::Easing, Value::String(value)) => {
+            if Easing::parse(value).is_none() {
+                report
+                    .error(path, format!("unknown easing '{}'", value))
+                    .help("use linear, ease-in, ease-out or ease-in-out");
+            }
+        }
+        (Kind::Blend, Value::String(value)) => {
+            if BlendSpace::parse(value).is_none() {
+                report
+                    .error(path, format!("unknown blend '{}'", value))
+                    .help("use linear or srgb");
+            }
+        }
+        (Kind::Indicator, Value::String(value)) => {
+            if Source::parse(value).is_none() {
+                report
+                    .error(path, format!("unknown indicator '{}'", value))
+                    .help("use battery or thermal");
+            }
+        }
+        (Kind::Thresholds, Value::Array(entries)) => {
+            let mut strings = Vec::new();
+            for entry in entries {
+                match entry {
+                    Value::String(entry) => strings.push(entry.clone()),
+                    other => {
+                        report.error(path, format!("expected \"threshold:colour\" strings, got {}", describe(other)));
+                    }
+                }
+            }
+            match indicator::thresholds(&strings) {
+                Ok(thresholds) => {
+                    for (threshold, value) in thresholds {
+                        if let Err(e) = colour(value, palette) {
+                            report.error(path, format!("'{}:{}': {}", threshold, value, e));
+                        }
+                    }
+                }
+                Err(e) => {
+                    report
+                        .error(path, e)
+                        .help("e.g. \"20:ff5555\" for 20% battery or less, \"80:ffb86c\" for up to 80°C");
+                }
+            }
+        }
+        (Kind::Text, Value::String(_)) => {}
+        (kind, value) => {
+            let expected = match kind {
+                Kind::Pixels => "a number of pixels",
+                Kind::Unit => "a number between 0.0 and 1.0",
+                Kind::Bool => "true or false",
+                Kind::Colour => "a colour string",
+                Kind::ColourList => "a list of \"name:colour\" strings",
+                Kind::Duration => "a duration string such as \"300ms\"",
+                Kind::Interval => "a duration string with a unit such as \"5s\"",
+                Kind::Easing => "an easing name string",
+                Kind::Blend => "\"linear\" or \"srgb\"",
+                Kind::Indicator => "\"battery\" or \"thermal\"",
+                Kind::Thresholds => "a list of \"threshold:colour\" strings",
+                Kind::Text => "a string",
+            };
+            let diagnostic = report.error(path, format!("expected {}, got {}", expected, describe(value)));
+            // The most common slip: quoting a number or a boolean
+            if let Value::String(s) = value
+                && (s.parse::<f64>().is_ok() || s == "true" || s == "false")
+            {
+                diagnostic.help(format!("remove the quotes: {}", s));
+            }
+        }
+    }
+}
+
+fn check_section(
+    set: &ConfigSet,
+    config: &dyn ConfigSource,
+    section: &str,
+    keys: &[&str],
+    known: &[(&str, Kind)],
+    palette: &mut Palette,
+    report: &mut Report,
+) {
+    for key in keys {
+        let path = format!("{}.{}", section, key);
+        let Some((_, kind)) = known.iter().find(|(name, _)| name == key) else {
+            let diagnostic = report.warning(&path, format!("unknown key '{}'", key));
+            if let Some(suggestion) = closest(key, known.iter().map(|(name, _)| *name)) {
+                diagnostic.help(format!("did you mean '{}'?", suggestion));
+            }
+            continue;
+        };
+        match file_value(set, config, &path) {
+            Ok(value) => check_value(&path, *kind, &value, palette, report),
+            Err(e) => {
+                report.error(&path, e.to_string());
+            }
+        }
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("the string \"{}\"", s),
+        Value::Number(n) => format!("the number {}", n),
+        Value::Bool(b) => format!("{}", b),
+        Value::Array(_) => "a list".to_string(),
+        Value::Object(_) => "a block".to_string(),
+        Value::Null => "null".to_string(),
+        _ => "an expression".to_string(),
+    }
+}
+
+/// The closest candidate within a couple of edits, for "did you mean" hints
+fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_lowercase();
+    candidates
+        .map(|candidate| (edit_distance(&word, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 4))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn print_diagnostic(line: Option<usize>, diagnostic: &Diagnostic) {
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let location = match line {
+        Some(line) => format!("{}:{}", diagnostic.file.display(), line),
+        None => diagnostic.file.display().to_string(),
+    };
+    println!("{}: {}: {}: {}", location, severity, diagnostic.key, diagnostic.message);
+    if let Some(help) = &diagnostic.help {
+        println!("    help: {}", help);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{lock_env, TempDir};
+    use std::sync::MutexGuard;
+
+    /// Keep the geometry checks away from any real compositor
+    fn without_compositor() -> MutexGuard<'static, ()> {
+        let env = lock_env();
+        // SAFETY: the env lock is held
+        unsafe {
+            std::env::remove_var("WAYLAND_SOCKET");
+            std::env::set_var("WAYLAND_DISPLAY", "snug-test-no-compositor");
+        }
+        env
+    }
+
+    /// A diagnostic as (file relative to the config dir, line, severity, key, message, help)
+    type Found = (String, Option<usize>, Severity, String, String, Option<String>);
+
+    /// Diagnostics for a config of `files`, the first being the main file
+    fn check_files(files: &[(&str, &str)]) -> Vec<Found> {
+        let _env = without_compositor();
+        let dir = TempDir::new("check");
+        let paths: Vec<PathBuf> = files.iter().map(|(name, contents)| dir.write(name, contents)).collect();
+        let set = ConfigSet::load(&paths[0]).unwrap();
+        let mut report = Report::default();
+        check_config(&set, &mut report);
+        report
+            .diagnostics
+            .into_iter()
+            .map(|d| {
+                let line = set.line_of(&d.file, &d.key);
+                let file = d.file.strip_prefix(dir.path()).unwrap_or(&d.file).display().to_string();
+                (file, line, d.severity, d.key, d.message, d.help)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clean_config_has_no_diagnostics() {
+        let diagnostics = check_files(&[(
+            "snug.rune",
+            "DP-1:\n  radius = 15\n  color = \"161b22\"\n  opacity = 0.9\n  shadow_enabled = true\nend\n",
+        )]);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn unknown_keys_get_suggestions() {
+        let diagnostics = check_files(&[(
+            "snug.rune",
+            "DP-1:\n  radius = 15\n  radus = 4\nend\n\nhyprland:\n  sync_gap = true\nend\n\nDP1:\n  radius = 0\nend\n",
+        )]);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|(_, line, severity, key, _, help)| (*line, severity, key.as_str(), help.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Some(3), &Severity::Warning, "DP-1.radus", Some("did you mean 'radius'?")),
+                (Some(7), &Severity::Warning, "hyprland.sync_gap", Some("did you mean 'sync_gaps'?")),
+                (Some(10), &Severity::Warning, "DP1", Some("did you mean 'DP-1'?")),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_types_and_ranges_are_errors() {
+        let diagnostics = check_files(&[(
+            "snug.rune",
+            "DP-1:\n  radius = \"15\"\n  opacity = 1.5\n  shadow_opacity = 80\n  shadow_enabled = \"yes\"\nend\n",
+        )]);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|(_, line, severity, _, message, help)| (*line, severity, message.as_str(), help.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Some(2), &Severity::Error, "expected a number of pixels, got the string \"15\"", Some("remove the quotes: 15")),
+                (Some(3), &Severity::Error, "must be between 0.0 and 1.0, got 1.5", Some("for 1.5% write 0.015")),
+                (Some(4), &Severity::Error, "must be between 0.0 and 1.0, got 80", Some("for 80% write 0.8")),
+                (Some(5), &Severity::Error, "expected true or false, got the string \"yes\"", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn problems_point_at_the_file_and_line_that_set_them() {
+        let diagnostics = check_files(&[
+            ("snug.rune", "include \"theme.rune\"\n\nDP-1:\n  radius = 15\nend\n"),
+            ("theme.rune", "DP-1:\n  color = \"161b22\"\n  left = 2.5\nend\n"),
+            ("conf.d/10-late.rune", "\nDP-1:\n  blend = \"cmyk\"\nend\n"),
+        ]);
+        let found: Vec<_> = diagnostics.iter().map(|(file, line, _, key, _, _)| (file.as_str(), *line, key.as_str())).collect();
+        assert_eq!(found, [("theme.rune", Some(3), "DP-1.left"), ("conf.d/10-late.rune", Some(3), "DP-1.blend")]);
+    }
+
+    #[test]
+    fn run_fails_on_errors_only() {
+        let _env = without_compositor();
+        let dir = TempDir::new("check-run");
+        let warned = dir.write("warned.rune", "DP-1:\n  radius = 0\n  radus = 4\nend\n");
+        assert!(run(Some(&warned)).unwrap());
+        let broken = dir.write("broken.rune", "DP-1:\n  radius = 0\n  opacity = 2\nend\n");
+        assert!(!run(Some(&broken)).unwrap());
+        let unparsable = dir.write("unparsable.rune", "DP-1:\n  radius = \nend\n");
+        assert!(!run(Some(&unparsable)).unwrap());
+        assert!(!run(Some(&dir.path().join("missing.rune"))).unwrap());
+    }
+
+    fn check_list(entries: &[&str], palette: &mut Palette) -> Vec<String> {
+        let value = Value::Array(entries.iter().map(|entry| Value::String(entry.to_string())).collect());
+        let mut report = Report::default();
+        check_value("DP-1.workspace_colors", Kind::ColourList, &value, palette, &mut report);
+        report.diagnostics.into_iter().map(|diagnostic| diagnostic.message).collect()
+    }
+
+    #[test]
+    fn colour_list_accepts_the_readme_example() {
+        let errors = check_list(&["1:161b22", "2:1f2937", "special:magic:3b2e4a"], &mut Palette::default());
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn colour_list_resolves_palette_names() {
+        let mut palette = Palette::default();
+        palette.define("bg", "161b22").unwrap();
+        assert!(check_list(&["3:bg", "special:scratch:bg"], &mut palette).is_empty());
+    }
+
+    #[test]
+    fn colour_list_reports_bad_entries() {
+        let errors = check_list(&["161b22", "1:nonsense"], &mut Palette::default());
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0], "expected \"name:colour\", got '161b22'");
+        assert!(errors[1].starts_with("'1:nonsense': "), "{}", errors[1]);
+    }
+}
@@ -1,28 +1,570 @@
-pub fn parse_colour(hex: &str, opacity_override: Option<f64>) -> (u8, u8, u8, u8) {
-    let hex = hex.trim_start_matches('#');
-    
-    // Parse RGB components
-    let (r, g, b) = if hex.len() >= 6 {
-        (
-            u8::from_str_radix(&hex[0..2], 16).unwrap_or(0),
-            u8::from_str_radix(&hex[2..4], 16).unwrap_or(0),
-            u8::from_str_radix(&hex[4..6], 16).unwrap_or(0),
-        )
+use std::fmt;
+
+/// A colour string that could not be understood
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColourError {
+    pub input: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ColourError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid colour '{}': {}", self.input, self.reason)
+    }
+}
+
+impl std::error::Error for ColourError {}
+
+/// A gamma-encoded sRGB colour with straight (non-premultiplied) alpha,
+/// every component in 0.0..=1.0
+///
+/// This is the single colour representation for the frame, shadow and
+/// anything else drawn; parse config strings with [`Colour::parse`] and
+/// convert to linear light or premultiplied pixels at the point of drawing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colour {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+/// A colour in linear light with straight alpha
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearColour {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Colour {
+    pub const BLACK: Self = Self { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+    pub const WHITE: Self = Self { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+
+    /// Parse a colour string
+    ///
+    /// Accepts hex (`fff`, `ffff`, `161b22`, `#161b22cc`), `rgb()`/`rgba()`,
+    /// `hsl()`/`hsla()`, `oklch()` and CSS named colours.
+    pub fn parse(input: &str) -> Result<Self, ColourError> {
+        let (r, g, b, a) = parse_rgba(input).map_err(|reason| ColourError {
+            input: input.to_string(),
+            reason,
+        })?;
+        Ok(Self::from_rgba8(r, g, b, a))
+    }
+
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let unit = |v: u8| v as f64 / 255.0;
+        Self { r: unit(r), g: unit(g), b: unit(b), a: unit(a) }
+    }
+
+    pub fn to_rgba8(self) -> (u8, u8, u8, u8) {
+        (unit_to_u8(self.r), unit_to_u8(self.g), unit_to_u8(self.b), unit_to_u8(self.a))
+    }
+
+    /// Replace the alpha channel, clamping to 0.0..=1.0
+    pub fn with_alpha(self, alpha: f64) -> Self {
+        Self { a: alpha.clamp(0.0, 1.0), ..self }
+    }
+
+    /// Apply an optional opacity override, which replaces the colour's own alpha
+    pub fn with_opacity(self, opacity: Option<f64>) -> Self {
+        match opacity {
+            Some(opacity) => self.with_alpha(opacity),
+            None => self,
+        }
+    }
+
+    pub fn to_linear(self) -> LinearColour {
+        LinearColour {
+            r: srgb_to_linear(self.r),
+            g: srgb_to_linear(self.g),
+            b: srgb_to_linear(self.b),
+            a: self.a,
+        }
+    }
+
+    /// Premultiplied sRGB components as (r, g, b, a)
+    pub fn premultiplied(self) -> (f64, f64, f64, f64) {
+        (self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// One premultiplied pixel in `wl_shm` ARGB8888 byte order (little endian B, G, R, A)
+    pub fn to_argb8888(self) -> [u8; 4] {
+        let (r, g, b, a) = self.premultiplied();
+        [unit_to_u8(b), unit_to_u8(g), unit_to_u8(r), unit_to_u8(a)]
+    }
+
+    /// Interpolate towards `other` in linear light, `t` = 0.0 gives `self`
+    ///
+    /// Mixing gamma-encoded values directly dips through muddy, too-dark
+    /// midpoints; going through linear light keeps fades even.
+    pub fn mix(self, other: Self, t: f64) -> Self {
+        let (from, to) = (self.to_linear(), other.to_linear());
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        LinearColour {
+            r: lerp(from.r, to.r),
+            g: lerp(from.g, to.g),
+            b: lerp(from.b, to.b),
+            a: lerp(from.a, to.a),
+        }
+        .to_srgb()
+    }
+
+    /// Eight digit hex (`rrggbbaa`), understood by [`Colour::parse`]
+    pub fn to_hex(self) -> String {
+        let (r, g, b, a) = self.to_rgba8();
+        format!("{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+impl LinearColour {
+    pub fn to_srgb(self) -> Colour {
+        Colour {
+            r: linear_to_srgb(self.r),
+            g: linear_to_srgb(self.g),
+            b: linear_to_srgb(self.b),
+            a: self.a,
+        }
+    }
+}
+
+fn parse_rgba(input: &str) -> Result<(u8, u8, u8, u8), String> {
+    let trimmed = input.trim();
+    let lower = trimmed.to_ascii_lowercase();
+
+    if lower.is_empty() {
+        return Err("empty colour".to_string());
+    }
+
+    if let Some((function, args)) = split_function(&lower)? {
+        let args = split_args(args);
+        return match function {
+            "rgb" | "rgba" => parse_rgb_function(&args),
+            "hsl" | "hsla" => parse_hsl_function(&args),
+            "oklch" => parse_oklch_function(&args),
+            other => Err(format!("unknown colour function '{}()'", other)),
+        };
+    }
+
+    let hex = lower.strip_prefix('#').unwrap_or(&lower);
+    if hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return parse_hex(hex);
+    }
+
+    if lower.starts_with('#') {
+        return Err("hex colours may only contain 0-9 and a-f".to_string());
+    }
+
+    named_colour(&lower).ok_or_else(|| {
+        "expected hex (161b22), rgb(), hsl(), oklch() or a CSS colour name".to_string()
+    })
+}
+
+/// Parse hex digits, the caller has already checked they are all 0-9a-f
+fn parse_hex(hex: &str) -> Result<(u8, u8, u8, u8), String> {
+    let digits: Vec<u8> = hex.chars().filter_map(|c| c.to_digit(16)).map(|d| d as u8).collect();
+    let channels: Vec<u8> = match digits.len() {
+        3 | 4 => digits.iter().map(|d| d * 17).collect(),
+        6 | 8 => digits.chunks(2).map(|pair| pair[0] * 16 + pair[1]).collect(),
+        n => return Err(format!("hex colours need 3, 4, 6 or 8 digits, got {}", n)),
+    };
+    Ok((channels[0], channels[1], channels[2], channels.get(3).copied().unwrap_or(255)))
+}
+
+/// Split `name(args)` into its parts, `None` when the input is not a function
+fn split_function(input: &str) -> Result<Option<(&str, &str)>, String> {
+    let Some(open) = input.find('(') else { return Ok(None) };
+    let Some(args) = input[open + 1..].strip_suffix(')') else {
+        return Err("missing closing ')'".to_string());
+    };
+    Ok(Some((input[..open].trim(), args)))
+}
+
+/// Split function arguments in either the legacy comma form or the modern
+/// space form with an optional `/ alpha`
+fn split_args(args: &str) -> Vec<&str> {
+    args.split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+fn parse_number(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("'{}' is not a number", value))
+}
+
+/// Parse a number or percentage, scaling percentages so 100% maps to `full`
+fn parse_scaled(value: &str, full: f64) -> Result<f64, String> {
+    match value.strip_suffix('%') {
+        Some(percent) => Ok(parse_number(percent)? / 100.0 * full),
+        None => parse_number(value),
+    }
+}
+
+fn parse_alpha(value: Option<&&str>) -> Result<u8, String> {
+    match value {
+        Some(value) => Ok(unit_to_u8(parse_scaled(value, 1.0)?)),
+        None => Ok(255),
+    }
+}
+
+/// Parse an angle in degrees, accepting `deg`, `rad`, `grad` and `turn` units
+fn parse_hue(value: &str) -> Result<f64, String> {
+    let degrees = if let Some(v) = value.strip_suffix("deg") {
+        parse_number(v)?
+    } else if let Some(v) = value.strip_suffix("grad") {
+        parse_number(v)? * 0.9
+    } else if let Some(v) = value.strip_suffix("rad") {
+        parse_number(v)?.to_degrees()
+    } else if let Some(v) = value.strip_suffix("turn") {
+        parse_number(v)? * 360.0
     } else {
-        (0, 0, 0)
+        parse_number(value)?
     };
-    
-    // Parse alpha component if present (8 hex digits = RGBA)
-    let a = if let Some(opacity) = opacity_override {
-        // Opacity override takes precedence
-        (opacity.clamp(0.0, 1.0) * 255.0) as u8
-    } else if hex.len() >= 8 {
-        // Parse alpha from hex string
-        u8::from_str_radix(&hex[6..8], 16).unwrap_or(255)
+    Ok(degrees.rem_euclid(360.0))
+}
+
+fn expect_args(args: &[&str], function: &str) -> Result<(), String> {
+    if args.len() == 3 || args.len() == 4 {
+        Ok(())
     } else {
-        // Default to fully opaque
-        255
+        Err(format!("{}() takes 3 components and an optional alpha, got {}", function, args.len()))
+    }
+}
+
+fn parse_rgb_function(args: &[&str]) -> Result<(u8, u8, u8, u8), String> {
+    expect_args(args, "rgb")?;
+    let channel = |value: &str| parse_scaled(value, 255.0).map(|v| v.clamp(0.0, 255.0).round() as u8);
+    Ok((channel(args[0])?, channel(args[1])?, channel(args[2])?, parse_alpha(args.get(3))?))
+}
+
+fn parse_hsl_function(args: &[&str]) -> Result<(u8, u8, u8, u8), String> {
+    expect_args(args, "hsl")?;
+    let hue = parse_hue(args[0])?;
+    let saturation = parse_scaled(args[1], 1.0)?.clamp(0.0, 1.0);
+    let lightness = parse_scaled(args[2], 1.0)?.clamp(0.0, 1.0);
+    let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
+    Ok((unit_to_u8(r), unit_to_u8(g), unit_to_u8(b), parse_alpha(args.get(3))?))
+}
+
+fn parse_oklch_function(args: &[&str]) -> Result<(u8, u8, u8, u8), String> {
+    expect_args(args, "oklch")?;
+    let lightness = parse_scaled(args[0], 1.0)?.clamp(0.0, 1.0);
+    // CSS maps 100% chroma to 0.4
+    let chroma = parse_scaled(args[1], 0.4)?.max(0.0);
+    let hue = parse_hue(args[2])?;
+    let (r, g, b) = oklch_to_srgb(lightness, chroma, hue);
+    Ok((unit_to_u8(r), unit_to_u8(g), unit_to_u8(b), parse_alpha(args.get(3))?))
+}
+
+fn unit_to_u8(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
     };
-    
-    (r, g, b, a)
+    let m = lightness - chroma / 2.0;
+    (r + m, g + m, b + m)
+}
+
+/// OKLCH to gamma-encoded sRGB, clipping anything out of gamut
+fn oklch_to_srgb(lightness: f64, chroma: f64, hue: f64) -> (f64, f64, f64) {
+    let (a, b) = (chroma * hue.to_radians().cos(), chroma * hue.to_radians().sin());
+
+    let l = (lightness + 0.396_337_777_4 * a + 0.215_803_757_3 * b).powi(3);
+    let m = (lightness - 0.105_561_345_8 * a - 0.063_854_172_8 * b).powi(3);
+    let s = (lightness - 0.089_484_177_5 * a - 1.291_485_548 * b).powi(3);
+
+    let r = 4.076_741_662_1 * l - 3.307_711_591_3 * m + 0.230_969_929_2 * s;
+    let g = -1.268_438_004_6 * l + 2.609_757_401_1 * m - 0.341_319_396_5 * s;
+    let b = -0.004_196_086_3 * l - 0.703_418_614_7 * m + 1.707_614_701 * s;
+
+    (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+}
+
+pub fn srgb_to_linear(value: f64) -> f64 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f64) -> f64 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn named_colour(name: &str) -> Option<(u8, u8, u8, u8)> {
+    if name == "transparent" {
+        return Some((0, 0, 0, 0));
+    }
+    let index = NAMED_COLOURS.binary_search_by(|(n, _)| n.cmp(&name)).ok()?;
+    let rgb = NAMED_COLOURS[index].1;
+    Some(((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255))
+}
+
+/// CSS named colours, sorted for binary search
+const NAMED_COLOURS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(input: &str) -> (u8, u8, u8, u8) {
+        Colour::parse(input).unwrap_or_else(|e| panic!("{}", e)).to_rgba8()
+    }
+
+    #[test]
+    fn hex_lengths() {
+        assert_eq!(rgba("f0a"), (0xff, 0x00, 0xaa, 0xff));
+        assert_eq!(rgba("f0a8"), (0xff, 0x00, 0xaa, 0x88));
+        assert_eq!(rgba("161b22"), (0x16, 0x1b, 0x22, 0xff));
+        assert_eq!(rgba("#161B22cc"), (0x16, 0x1b, 0x22, 0xcc));
+        assert_eq!(rgba("  #fff  "), (0xff, 0xff, 0xff, 0xff));
+    }
+
+    #[test]
+    fn rgb_functions() {
+        assert_eq!(rgba("rgb(22, 27, 34)"), (22, 27, 34, 255));
+        assert_eq!(rgba("rgba(22, 27, 34, 0.5)"), (22, 27, 34, 128));
+        assert_eq!(rgba("rgb(100% 0% 50% / 25%)"), (255, 0, 128, 64));
+        // Out of range channels clamp instead of wrapping
+        assert_eq!(rgba("rgb(300 -5 0)"), (255, 0, 0, 255));
+    }
+
+    #[test]
+    fn hsl_functions() {
+        assert_eq!(rgba("hsl(0, 100%, 50%)"), (255, 0, 0, 255));
+        assert_eq!(rgba("hsl(120deg 100% 25%)"), (0, 128, 0, 255));
+        assert_eq!(rgba("hsla(0.5turn, 100%, 50%, 0.5)"), (0, 255, 255, 128));
+        // Hues wrap around the circle
+        assert_eq!(rgba("hsl(-120 100% 50%)"), rgba("hsl(240 100% 50%)"));
+    }
+
+    #[test]
+    fn oklch_functions() {
+        assert_eq!(rgba("oklch(100% 0 0)"), (255, 255, 255, 255));
+        assert_eq!(rgba("oklch(0 0 0)"), (0, 0, 0, 255));
+        // CSS red and blue, as the spec writes them
+        assert_eq!(rgba("oklch(62.8% 0.2577 29.23)"), (255, 0, 0, 255));
+        assert_eq!(rgba("oklch(45.2% 0.313 264.05 / 50%)"), (0, 0, 255, 128));
+    }
+
+    #[test]
+    fn named_colours() {
+        assert_eq!(rgba("rebeccapurple"), (0x66, 0x33, 0x99, 0xff));
+        assert_eq!(rgba("White"), (0xff, 0xff, 0xff, 0xff));
+        assert_eq!(rgba("transparent"), (0, 0, 0, 0));
+        assert!(NAMED_COLOURS.windows(2).all(|pair| pair[0].0 < pair[1].0), "not sorted for binary search");
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        for input in [
+            "",
+            "   ",
+            "#12345",
+            "1234567",
+            "#ggg",
+            "rgb(1, 2)",
+            "rgb(1, 2, 3, 4, 5)",
+            "rgb(1, 2, 3",
+            "hsl(red, 100%, 50%)",
+            "cmyk(0, 0, 0, 0)",
+            "notacolour",
+            "rgb(nan, 0, 0)",
+        ] {
+            assert!(Colour::parse(input).is_err(), "'{}' parsed", input);
+        }
+        let error = Colour::parse("#12345").unwrap_err();
+        assert_eq!(error.to_string(), "invalid colour '#12345': hex colours need 3, 4, 6 or 8 digits, got 5");
+    }
+
+    #[test]
+    fn mix_endpoints_and_midpoint() {
+        let from = Colour::parse("161b22").unwrap();
+        let to = Colour::parse("ff000080").unwrap();
+        assert_eq!(from.mix(to, 0.0).to_rgba8(), from.to_rgba8());
+        assert_eq!(from.mix(to, 1.0).to_rgba8(), to.to_rgba8());
+        // Halfway between black and white in linear light is lighter than 50% grey
+        assert_eq!(Colour::BLACK.mix(Colour::WHITE, 0.5).to_rgba8(), (188, 188, 188, 255));
+    }
+
+    #[test]
+    fn argb8888_is_premultiplied_bgra() {
+        assert_eq!(Colour::parse("ff8000").unwrap().to_argb8888(), [0x00, 0x80, 0xff, 0xff]);
+        assert_eq!(Colour::parse("ff800080").unwrap().to_argb8888(), [0x00, 0x40, 0x80, 0x80]);
+        assert_eq!(Colour::parse("ffffff00").unwrap().to_argb8888(), [0, 0, 0, 0]);
+        assert_eq!(Colour::WHITE.with_opacity(Some(0.5)).to_argb8888(), [128, 128, 128, 128]);
+    }
 }
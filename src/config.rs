@@ -1,8 +1,18 @@
-use std::path::PathBuf;
+use crate::animation::Easing;
+use crate::args::{Args, ConfigAction};
+use crate::colour::Colour;
+use crate::drawing::BlendSpace;
+use crate::indicator::{self, Source};
+use crate::palette::{self, Palette};
+use crate::schedule::Schedule;
+use crate::paths::{expand_tilde, locate_config, ConfigOrigin};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::collections::HashMap;
 use eyre::{Result, eyre};
-use rune_cfg::RuneConfig;
+use crate::config_set::ConfigSet;
 use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DisplayConfig {
@@ -18,6 +28,85 @@ pub struct DisplayConfig {
     pub shadow_color: Option<String>,
     pub shadow_opacity: Option<f64>,
     pub shadow_blur: Option<f64>,
+    /// Colour space for edge and shadow blending, `linear` or `srgb`
+    pub blend: Option<String>,
+    // Compositor integration properties
+    pub workspace_colors: Option<Vec<String>>,
+    pub hide_on_fullscreen: Option<bool>,
+    pub overview_opacity: Option<f64>,
+    pub mode_colors: Option<Vec<String>>,
+    pub urgent_color: Option<String>,
+    // Config reload transition
+    pub transition_duration: Option<String>,
+    pub transition_easing: Option<String>,
+    // sysfs indicator: `battery` or `thermal`, and `"threshold:colour"` entries
+    pub indicator: Option<String>,
+    pub indicator_colors: Option<Vec<String>>,
+    pub indicator_charging_color: Option<String>,
+    pub indicator_device: Option<String>,
+    /// Command or FIFO emitting JSON lines that drive the frame at runtime
+    pub source: Option<String>,
+}
+
+impl DisplayConfig {
+    /// Look up the frame colour for a workspace from `workspace_colors`
+    pub fn workspace_color(&self, workspace: &str) -> Option<&str> {
+        lookup_colour(self.workspace_colors.as_deref()?, workspace)
+    }
+
+    /// Reload transition length, `None` when transitions are off or invalid
+    pub fn transition(&self) -> Option<(Duration, Easing)> {
+        let duration = crate::args::parse_duration(self.transition_duration.as_deref()?).ok()?;
+        if duration.is_zero() {
+            return None;
+        }
+        let easing = self.transition_easing
+            .as_deref()
+            .and_then(Easing::parse)
+            .unwrap_or(Easing::EaseOut);
+        Some((duration, easing))
+    }
+
+    /// Look up the frame colour for a compositor binding mode from `mode_colors`
+    pub fn mode_color(&self, mode: &str) -> Option<&str> {
+        lookup_colour(self.mode_colors.as_deref()?, mode)
+    }
+}
+
+impl DisplayConfig {
+    /// Every colour value as written, including the colour half of list entries
+    fn colours(&self) -> impl Iterator<Item = &str> {
+        let single = [&self.shadow_color, &self.urgent_color, &self.indicator_charging_color]
+            .into_iter()
+            .flatten()
+            .map(String::as_str);
+        let lists = [&self.workspace_colors, &self.mode_colors, &self.indicator_colors]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| split_colour_entry(entry).map(|(_, colour)| colour));
+        std::iter::once(self.color.as_str()).chain(single).chain(lists)
+    }
+}
+
+/// Split a `"name:colour"` entry on its last colon
+///
+/// Names may contain colons, like Hyprland's `special:magic`; a trailing
+/// `wal:colorN` reference stays whole.
+pub fn split_colour_entry(entry: &str) -> Option<(&str, &str)> {
+    let (name, colour) = entry.rsplit_once(':')?;
+    match name.strip_suffix(":wal") {
+        Some(name) => Some((name, &entry[name.len() + 1..])),
+        None => Some((name, colour)),
+    }
+}
+
+/// Find `name` in a list of `"name:colour"` entries
+fn lookup_colour<'a>(entries: &'a [String], name: &str) -> Option<&'a str> {
+    entries.iter().find_map(|entry| {
+        let (key, colour) = split_colour_entry(entry)?;
+        (key.trim() == name).then(|| colour.trim())
+    })
 }
 
 impl Default for DisplayConfig {
@@ -34,6 +123,78 @@ impl Default for DisplayConfig {
             shadow_color: None,
             shadow_opacity: None,
             shadow_blur: None,
+            blend: None,
+            workspace_colors: None,
+            hide_on_fullscreen: None,
+            overview_opacity: None,
+            mode_colors: None,
+            urgent_color: None,
+            transition_duration: None,
+            transition_easing: None,
+            indicator: None,
+            indicator_colors: None,
+            indicator_charging_color: None,
+            indicator_device: None,
+            source: None,
+        }
+    }
+}
+
+/// Settings for the Hyprland IPC integration (`hyprland:` block)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HyprlandConfig {
+    pub enabled: bool,
+    /// Use Hyprland's `general:gaps_out` as the border widths
+    pub sync_gaps: bool,
+}
+
+impl Default for HyprlandConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sync_gaps: false,
+        }
+    }
+}
+
+/// Settings for the niri IPC integration (`niri:` block)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NiriConfig {
+    pub enabled: bool,
+}
+
+impl Default for NiriConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Settings for the sway / i3 IPC integration (`sway:` block)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwayConfig {
+    pub enabled: bool,
+}
+
+impl Default for SwayConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Settings shared by every display's sysfs indicator (`indicator:` block)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicatorConfig {
+    /// Where sysfs is mounted, point it at a fake tree to test thresholds
+    pub sysfs_root: PathBuf,
+    /// How often to read sysfs
+    pub interval: Duration,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self {
+            sysfs_root: PathBuf::from("/sys"),
+            interval: Duration::from_secs(5),
         }
     }
 }
@@ -41,135 +202,445 @@ impl Default for DisplayConfig {
 #[derive(Debug, Clone)]
 pub struct SnugConfig {
     pub displays: HashMap<String, DisplayConfig>,
+    /// Display blocks of each `profiles:` entry, already merged over the base blocks
+    pub profiles: HashMap<String, HashMap<String, DisplayConfig>>,
+    /// Profile whose display blocks take precedence, if any
+    pub active_profile: Option<String>,
+    /// Switches profiles by time of day
+    pub schedule: Option<Schedule>,
+    pub hyprland: HyprlandConfig,
+    pub niri: NiriConfig,
+    pub sway: SwayConfig,
+    pub indicator: IndicatorConfig,
+}
+
+impl Default for SnugConfig {
+    fn default() -> Self {
+        let mut displays = HashMap::new();
+        displays.insert("default".to_string(), DisplayConfig::default());
+        Self {
+            displays,
+            profiles: HashMap::new(),
+            active_profile: None,
+            schedule: None,
+            hyprland: HyprlandConfig::default(),
+            niri: NiriConfig::default(),
+            sway: SwayConfig::default(),
+            indicator: IndicatorConfig::default(),
+        }
+    }
 }
 
 impl SnugConfig {
     pub fn get_display_config(&self, display_name: &str) -> DisplayConfig {
-        self.displays
-            .get(display_name)
+        self.display_config_for(self.active_profile.as_deref(), display_name)
+    }
+
+    /// A display's config under `profile`, whether or not it is the active one
+    pub fn display_config_for(&self, profile: Option<&str>, display_name: &str) -> DisplayConfig {
+        profile
+            .and_then(|profile| self.profiles.get(profile)?.get(display_name))
+            .or_else(|| self.displays.get(display_name))
             .cloned()
             .unwrap_or_default()
     }
-}
 
-/// Expands ~ to the home directory
-fn expand_tilde(path: &str) -> PathBuf {
-    if path.starts_with("~/") {
-        if let Some(home) = dirs::home_dir() {
-            return home.join(&path[2..]);
+    /// Make `name` the active profile, or go back to the base blocks with `None`
+    pub fn set_profile(&mut self, name: Option<&str>) -> Result<(), String> {
+        if let Some(name) = name
+            && !self.profiles.contains_key(name)
+        {
+            let mut known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            known.sort();
+            return Err(if known.is_empty() {
+                format!("unknown profile '{}', the config defines no profiles", name)
+            } else {
+                format!("unknown profile '{}', expected one of: {}", name, known.join(", "))
+            });
         }
+        self.active_profile = name.map(str::to_string);
+        Ok(())
+    }
+
+    /// Displays that get a frame under the active profile
+    pub fn display_names(&self) -> Vec<&str> {
+        let profile = self.active_profile.as_ref().and_then(|profile| self.profiles.get(profile));
+        let mut names: Vec<&str> = self
+            .displays
+            .keys()
+            .chain(profile.into_iter().flat_map(|displays| displays.keys()))
+            .map(String::as_str)
+            .filter(|name| *name != "default")
+            .collect();
+        names.sort();
+        names.dedup();
+        names
     }
-    PathBuf::from(path)
 }
 
-pub fn load_config(path: &str) -> Result<SnugConfig> {
+/// Environment variable selecting a profile, below `--profile` in precedence
+pub const PROFILE_ENV: &str = "SNUG_PROFILE";
+
+/// Profile asked for with `--profile` or `$SNUG_PROFILE`
+pub fn requested_profile(args: &Args) -> Option<String> {
+    args.profile
+        .clone()
+        .or_else(|| std::env::var(PROFILE_ENV).ok().filter(|p| !p.is_empty()))
+}
+
+/// Output names a display block may use
+pub const KNOWN_DISPLAYS: &[&str] = &[
+    "DP-1", "DP-2", "DP-3", "DP-4", 
+    "DP-5", "DP-6", "DP-7", "DP-8",
+    
+    "HDMI-A-1", "HDMI-A-2", "HDMI-A-3", "HDMI-A-4",
+    "HDMI-1", "HDMI-2", "HDMI-3", "HDMI-4",
+    
+    "eDP-1", "eDP-2",
+    
+    "DVI-D-1", "DVI-D-2",
+    "DVI-I-1", "DVI-I-2", 
+    
+    "HEADLESS-1", "HEADLESS-2",
+    "VIRTUAL1", "VIRTUAL2",
+];
+
+pub fn load_config(path: &Path) -> Result<SnugConfig> {
     load_config_internal(path, false)
 }
 
-pub fn load_config_silent(path: &str) -> Result<SnugConfig> {
+pub fn load_config_silent(path: &Path) -> Result<SnugConfig> {
     load_config_internal(path, true)
 }
 
-fn load_config_internal(path: &str, silent: bool) -> Result<SnugConfig> {
+fn load_config_internal(path: &Path, silent: bool) -> Result<SnugConfig> {
     let expanded_path = expand_tilde(path);
     
-    let config = RuneConfig::from_file(expanded_path.to_str().unwrap())
-        .map_err(|e| eyre!("Failed to load config: {}", e))?;
+    let config = ConfigSet::load(&expanded_path)?;
     
     let mut displays = HashMap::new();
 
-    let possible_displays = vec![
-        "DP-1", "DP-2", "DP-3", "DP-4", 
-        "DP-5", "DP-6", "DP-7", "DP-8",
-        
-        "HDMI-A-1", "HDMI-A-2", "HDMI-A-3", "HDMI-A-4",
-        "HDMI-1", "HDMI-2", "HDMI-3", "HDMI-4",
-        
-        "eDP-1", "eDP-2",
-        
-        "DVI-D-1", "DVI-D-2",
-        "DVI-I-1", "DVI-I-2", 
-        
-        "HEADLESS-1", "HEADLESS-2",
-        "VIRTUAL1", "VIRTUAL2",
-    ];
-    
-    for display in possible_displays {
-        if let Ok(_) = config.get::<i32>(&format!("{}.radius", display)) {
-            let display_config = DisplayConfig {
-                radius: config.get_or(&format!("{}.radius", display), 15),
-                left: config.get_or(&format!("{}.left", display), 30),
-                right: config.get_or(&format!("{}.right", display), 30),
-                top: config.get_or(&format!("{}.top", display), 30),
-                bottom: config.get_or(&format!("{}.bottom", display), 30),
-                color: config.get_or(&format!("{}.color", display), "000000".to_string()),
-                opacity: config.get(&format!("{}.opacity", display)).ok(),
-                shadow_enabled: config.get(&format!("{}.shadow_enabled", display)).ok(),
-                shadow_color: config.get(&format!("{}.shadow_color", display)).ok(),
-                shadow_opacity: config.get(&format!("{}.shadow_opacity", display)).ok(),
-                shadow_blur: config.get(&format!("{}.shadow_blur", display)).ok(),
-            };
-            displays.insert(display.to_string(), display_config);
+    
+    let mut palette = Palette::from_config(&config).map_err(|e| eyre!(e))?;
+    
+    for &name in KNOWN_DISPLAYS {
+        if let Some(display_config) = read_display(&config, &[name.to_string()], &mut palette)? {
+            displays.insert(name.to_string(), display_config);
             if !silent {
-                eprintln!("✓ Loaded config for display: {}", display);
+                debug!("Loaded config for display: {}", name);
             }
         }
     }
     
+    // Profile blocks override the base blocks key by key
+    let mut profiles = HashMap::new();
+    for name in config.get_keys("profiles").unwrap_or_default() {
+        let mut profile_displays = HashMap::new();
+        for display in config.get_keys(&format!("profiles.{}", name)).unwrap_or_default() {
+            if !KNOWN_DISPLAYS.contains(&display.as_str()) {
+                continue;
+            }
+            let layers = [format!("profiles.{}.{}", name, display), display.clone()];
+            if let Some(display_config) = read_display(&config, &layers, &mut palette)? {
+                profile_displays.insert(display, display_config);
+            }
+        }
+        if !silent {
+            debug!("Loaded profile: {}", name);
+        }
+        profiles.insert(name, profile_displays);
+    }
+    
     if displays.is_empty() {
         displays.insert("default".to_string(), DisplayConfig::default());
     }
     
-    Ok(SnugConfig { displays })
+    let hyprland = HyprlandConfig {
+        enabled: config.get_or("hyprland.enabled", true),
+        sync_gaps: config.get_or("hyprland.sync_gaps", false),
+    };
+    
+    let niri = NiriConfig {
+        enabled: config.get_or("niri.enabled", true),
+    };
+    
+    let sway = SwayConfig {
+        enabled: config.get_or("sway.enabled", true),
+    };
+    
+    let defaults = IndicatorConfig::default();
+    let indicator = IndicatorConfig {
+        sysfs_root: config
+            .get::<String>("indicator.sysfs_root")
+            .map(|root| expand_tilde(Path::new(&root)))
+            .unwrap_or(defaults.sysfs_root),
+        interval: match config.get::<String>("indicator.interval") {
+            Ok(value) => indicator::parse_interval(&value).map_err(|e| eyre!("indicator.interval: {}", e))?,
+            Err(_) => defaults.interval,
+        },
+    };
+    
+    let schedule = Schedule::from_config(&config)?;
+    if let Some(schedule) = &schedule
+        && let Some(unknown) = schedule.profiles().find(|profile| !profiles.contains_key(*profile))
+    {
+        return Err(eyre!("schedule.times: unknown profile '{}'", unknown));
+    }
+    
+    Ok(SnugConfig { displays, profiles, active_profile: None, schedule, hyprland, niri, sway, indicator })
 }
 
-pub fn find_config() -> Option<PathBuf> {
-    if let Some(home) = dirs::config_dir() {
-        let user_config = home.join("snug").join("snug.rune");
-        if user_config.exists() {
-            return Some(user_config);
+/// Build a display block, taking each key from the first of `layers` that sets it
+///
+/// `None` when no layer sets a radius, which is what marks a display as configured.
+fn read_display(config: &ConfigSet, layers: &[String], palette: &mut Palette) -> Result<Option<DisplayConfig>> {
+    let Some(mut display_config) = read_raw_display(config, layers) else {
+        return Ok(None);
+    };
+    resolve_colours(&layers[0], &mut display_config, palette)?;
+    validate_colours(&layers[0], &display_config)?;
+    Ok(Some(display_config))
+}
+
+/// A display block as written, colours unresolved; `None` without a radius
+fn read_raw_display(config: &ConfigSet, layers: &[String]) -> Option<DisplayConfig> {
+    let key = |field: &str| {
+        layers
+            .iter()
+            .map(|layer| format!("{}.{}", layer, field))
+            .find(|path| config.get_value(path).is_ok())
+            .unwrap_or_else(|| format!("{}.{}", layers[0], field))
+    };
+    config.get::<i32>(&key("radius")).ok()?;
+    
+    Some(DisplayConfig {
+        radius: config.get_or(&key("radius"), 15),
+        left: config.get_or(&key("left"), 30),
+        right: config.get_or(&key("right"), 30),
+        top: config.get_or(&key("top"), 30),
+        bottom: config.get_or(&key("bottom"), 30),
+        color: config.get_or(&key("color"), "000000".to_string()),
+        opacity: config.get(&key("opacity")).ok(),
+        shadow_enabled: config.get(&key("shadow_enabled")).ok(),
+        shadow_color: config.get(&key("shadow_color")).ok(),
+        shadow_opacity: config.get(&key("shadow_opacity")).ok(),
+        shadow_blur: config.get(&key("shadow_blur")).ok(),
+        blend: config.get(&key("blend")).ok(),
+        workspace_colors: config.get(&key("workspace_colors")).ok(),
+        hide_on_fullscreen: config.get(&key("hide_on_fullscreen")).ok(),
+        overview_opacity: config.get(&key("overview_opacity")).ok(),
+        mode_colors: config.get(&key("mode_colors")).ok(),
+        urgent_color: config.get(&key("urgent_color")).ok(),
+        transition_duration: config.get(&key("transition_duration")).ok(),
+        transition_easing: config.get(&key("transition_easing")).ok(),
+        indicator: config.get(&key("indicator")).ok(),
+        indicator_colors: config.get(&key("indicator_colors")).ok(),
+        indicator_charging_color: config.get(&key("indicator_charging_color")).ok(),
+        indicator_device: config.get(&key("indicator_device")).ok(),
+        source: config.get(&key("source")).ok(),
+    })
+}
+
+/// Whether any palette entry or display colour, in any profile, refers to the
+/// pywal / wallust scheme
+pub fn uses_wal(config: &ConfigSet) -> bool {
+    let mut entries = config
+        .get_keys("palette")
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| config.get::<String>(&format!("palette.{}", name)).ok());
+    if entries.any(|value| palette::mentions_wal(&value)) {
+        return true;
+    }
+
+    let mut layers: Vec<Vec<String>> = KNOWN_DISPLAYS.iter().map(|name| vec![name.to_string()]).collect();
+    for profile in config.get_keys("profiles").unwrap_or_default() {
+        for display in config.get_keys(&format!("profiles.{}", profile)).unwrap_or_default() {
+            layers.push(vec![format!("profiles.{}.{}", profile, display), display]);
         }
     }
-    let default_config = PathBuf::from("/usr/share/doc/snug/snug.rune");
-    if default_config.exists() {
-        return Some(default_config);
+    layers
+        .iter()
+        .filter_map(|layers| read_raw_display(config, layers))
+        .any(|display| display.colours().any(palette::mentions_wal))
+}
+
+/// Replace palette names, derived colours and `wal:` references with plain colours
+fn resolve_colours(display: &str, config: &mut DisplayConfig, palette: &mut Palette) -> Result<()> {
+    let mut resolve = |key: &str, value: &mut String| -> Result<()> {
+        *value = palette.resolve(value).map_err(|e| eyre!("{}.{}: {}", display, key, e))?;
+        Ok(())
+    };
+    
+    resolve("color", &mut config.color)?;
+    if let Some(colour) = &mut config.shadow_color {
+        resolve("shadow_color", colour)?;
+    }
+    if let Some(colour) = &mut config.urgent_color {
+        resolve("urgent_color", colour)?;
+    }
+    if let Some(colour) = &mut config.indicator_charging_color {
+        resolve("indicator_charging_color", colour)?;
+    }
+    for (key, entries) in [
+        ("workspace_colors", &mut config.workspace_colors),
+        ("mode_colors", &mut config.mode_colors),
+        ("indicator_colors", &mut config.indicator_colors),
+    ] {
+        for entry in entries.iter_mut().flatten() {
+            if let Some((name, colour)) = split_colour_entry(entry) {
+                let mut colour = colour.trim().to_string();
+                resolve(key, &mut colour)?;
+                *entry = format!("{}:{}", name, colour);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Report unparseable colours, blend modes and indicator settings instead of silently drawing them wrong
+fn validate_colours(display: &str, config: &DisplayConfig) -> Result<()> {
+    let check = |key: &str, value: &str| {
+        Colour::parse(value).map(|_| ()).map_err(|e| eyre!("{}.{}: {}", display, key, e))
+    };
+    
+    check("color", &config.color)?;
+    if let Some(colour) = &config.shadow_color {
+        check("shadow_color", colour)?;
+    }
+    if let Some(colour) = &config.urgent_color {
+        check("urgent_color", colour)?;
+    }
+    if let Some(colour) = &config.indicator_charging_color {
+        check("indicator_charging_color", colour)?;
+    }
+    for (key, entries) in [
+        ("workspace_colors", &config.workspace_colors),
+        ("mode_colors", &config.mode_colors),
+        ("indicator_colors", &config.indicator_colors),
+    ] {
+        for entry in entries.iter().flatten() {
+            let Some((_, colour)) = split_colour_entry(entry) else {
+                return Err(eyre!("{}.{}: expected \"name:colour\", got '{}'", display, key, entry));
+            };
+            check(key, colour.trim())?;
+        }
+    }
+    if let Some(blend) = &config.blend
+        && BlendSpace::parse(blend).is_none()
+    {
+        return Err(eyre!("{}.blend: expected \"linear\" or \"srgb\", got '{}'", display, blend));
+    }
+    if let Some(indicator) = &config.indicator {
+        if Source::parse(indicator).is_none() {
+            return Err(eyre!("{}.indicator: expected \"battery\" or \"thermal\", got '{}'", display, indicator));
+        }
+        if config.indicator_colors.is_none() && config.indicator_charging_color.is_none() {
+            return Err(eyre!("{}.indicator: set indicator_colors to say which colour each level gets", display));
+        }
+    }
+    if let Some(entries) = &config.indicator_colors {
+        indicator::thresholds(entries).map_err(|e| eyre!("{}.indicator_colors: {}", display, e))?;
+    }
+    Ok(())
+}
+
+/// Fail early on `snug msg profile <name>` when the config defines no such profile
+///
+/// Instances keep their last good config when a reload fails, so a config
+/// that doesn't load right now is not a reason to refuse the message.
+pub fn validate_profile(args: &Args, name: &str) -> Result<(), String> {
+    let location = locate_config(args.config.as_deref());
+    let Ok(mut config) = load_config_silent(&location.path) else {
+        return Ok(());
+    };
+    config.set_profile(Some(name))
+}
+
+/// The config file to load, if one exists
+pub fn find_config() -> Option<PathBuf> {
+    let location = locate_config(None);
+    if location.path.is_file() {
+        return Some(location.path);
+    }
+    if location.origin == ConfigOrigin::Environment {
+        warn!("${} points to {}, which does not exist", crate::paths::CONFIG_ENV, location.path.display());
     }
     None
 }
 
+/// Load the config again for a running instance
+///
+/// Unlike `load_config_or_default`, errors are returned so the caller can
+/// keep its last good config. With no config file the defaults apply.
+pub fn reload_config(cli: Option<&Path>) -> Result<SnugConfig> {
+    match cli.map(Path::to_path_buf).or_else(find_config) {
+        Some(path) => load_config_silent(&path),
+        None => Ok(SnugConfig::default()),
+    }
+}
+
 pub fn load_config_or_default() -> SnugConfig {
     match find_config() {
-        Some(path) => match load_config(&path.to_string_lossy()) {
+        Some(path) => match load_config(&path) {
             Ok(cfg) => cfg,
             Err(err) => {
-                eprintln!("❌ Configuration error: {}\nUsing defaults.", err);
-                SnugConfig {
-                    displays: {
-                        let mut map = HashMap::new();
-                        map.insert("default".to_string(), DisplayConfig::default());
-                        map
-                    }
-                }
+                error!("Configuration error: {}. Using defaults.", err);
+                SnugConfig::default()
             }
         },
-        None => {
-            SnugConfig {
-                displays: {
-                    let mut map = HashMap::new();
-                    map.insert("default".to_string(), DisplayConfig::default());
-                    map
-                }
+        None => SnugConfig::default(),
+    }
+}
+
+/// `snug config ...` subcommands
+pub fn run_config_command(action: &ConfigAction, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let cli_path = args.config.as_deref();
+    match action {
+        ConfigAction::Path => {
+            let location = locate_config(cli_path);
+            // The path alone on stdout, so `$EDITOR "$(snug config path)"` works
+            println!("{}", location.path.display());
+            if location.origin != ConfigOrigin::Missing && !location.path.exists() {
+                eprintln!("from {} (does not exist)", location.origin);
+            } else {
+                eprintln!("from {}", location.origin);
             }
         }
+        ConfigAction::Dump { display, format } => {
+            let display = display.as_deref().or(args.display.as_deref());
+            crate::dump::run(args, display, *format)?;
+        }
     }
+    Ok(())
 }
 
-pub fn get_config_path() -> PathBuf {
-    find_config().unwrap_or_else(|| {
-        if let Some(config_dir) = dirs::config_dir() {
-            config_dir.join("snug").join("snug.rune")
-        } else {
-            PathBuf::from("snug.rune")
-        }
-    })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn colour_entries_split_on_the_last_colon() {
+        assert_eq!(split_colour_entry("1:161b22"), Some(("1", "161b22")));
+        assert_eq!(split_colour_entry("special:magic:3b2e4a"), Some(("special:magic", "3b2e4a")));
+        assert_eq!(split_colour_entry("2:wal:color4"), Some(("2", "wal:color4")));
+        assert_eq!(split_colour_entry("special:magic:wal:color1"), Some(("special:magic", "wal:color1")));
+        assert_eq!(split_colour_entry("161b22"), None);
+    }
+
+    #[test]
+    fn reload_reports_errors_instead_of_using_defaults() {
+        let dir = TempDir::new("reload");
+        let path = dir.path().join("snug.rune");
+
+        fs::write(&path, "DP-1:\n  radius = 0\n  color = \"161b22\"\nend\n").unwrap();
+        let config = reload_config(Some(&path)).unwrap();
+        assert_eq!(config.get_display_config("DP-1").color, "161b22");
+
+        fs::write(&path, "DP-1:\n  radius = 0\n  color = \"not-a-colour\"\nend\n").unwrap();
+        let error = reload_config(Some(&path)).unwrap_err();
+        assert!(error.to_string().contains("DP-1.color"), "{}", error);
+    }
 }
@@ -0,0 +1,266 @@
+use crate::config_source::{self, ConfigSource};
+use crate::paths::expand_tilde;
+use eyre::{Result, eyre};
+use rune_cfg::{RuneError, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How deep `palette.bg`-style references may chain before we call it a cycle
+const MAX_REFERENCE_DEPTH: usize = 16;
+
+/// A config file together with everything it pulls in
+///
+/// Files are kept in precedence order, lowest first:
+///
+/// 1. files named by `include "theme.rune"`, before the file including them
+/// 2. the main config file
+/// 3. every `conf.d/*.{rune,toml,json}` next to the main file, sorted by name
+///
+/// Files may be in any format `config_source` understands and can be mixed.
+///
+/// Lookups take a key from the last file that sets it, so a display block
+/// can be split across files and each key overridden on its own.
+pub struct ConfigSet {
+    files: Vec<(PathBuf, Box<dyn ConfigSource>)>,
+    /// Line of every `block.key` in each file, for diagnostics and provenance
+    lines: HashMap<PathBuf, HashMap<String, usize>>,
+    conf_dir: PathBuf,
+}
+
+impl ConfigSet {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut set = Self {
+            files: Vec::new(),
+            lines: HashMap::new(),
+            conf_dir: conf_dir(path),
+        };
+        let mut visiting = HashSet::new();
+        set.add_file(path, &mut visiting)?;
+
+        for fragment in conf_d_files(&set.conf_dir) {
+            set.add_file(&fragment, &mut visiting)?;
+        }
+        Ok(set)
+    }
+
+    /// Load `path` after the files it includes
+    fn add_file(&mut self, path: &Path, visiting: &mut HashSet<PathBuf>) -> Result<()> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if !visiting.insert(canonical.clone()) {
+            return Err(eyre!("{}: include cycle, the file includes itself", path.display()));
+        }
+
+        let config = config_source::load(path)?;
+
+        let base = path.parent().unwrap_or(Path::new("."));
+        for include in config.includes() {
+            let included = base.join(expand_tilde(Path::new(&include)));
+            if !included.exists() {
+                return Err(eyre!("{}: included file not found: {}", path.display(), included.display()));
+            }
+            self.add_file(&included, visiting)?;
+        }
+
+        let content = fs::read_to_string(path).unwrap_or_default();
+        self.lines.insert(path.to_path_buf(), config.key_lines(&content));
+        self.files.push((path.to_path_buf(), config));
+        visiting.remove(&canonical);
+        Ok(())
+    }
+
+    /// Every loaded file, lowest precedence first
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &dyn ConfigSource)> {
+        self.files.iter().map(|(path, config)| (path.as_path(), config.as_ref()))
+    }
+
+    /// `conf.d` directory next to the main file, which may not exist
+    pub fn conf_dir(&self) -> &Path {
+        &self.conf_dir
+    }
+
+    /// Line where `file` sets `key`
+    pub fn line_of(&self, file: &Path, key: &str) -> Option<usize> {
+        self.lines.get(file)?.get(key).copied()
+    }
+
+    /// Last file that sets `key`, which is where the merged value came from
+    pub fn source_of(&self, key: &str) -> Option<(&Path, Option<usize>)> {
+        let (file, _) = self.files.iter().rev().find(|(_, config)| config.get_value(key).is_ok())?;
+        Some((file.as_path(), self.line_of(file, key)))
+    }
+
+    /// Raw value from the highest-precedence file that sets `path`,
+    /// with references resolved across all files
+    pub fn get_value(&self, path: &str) -> Result<Value, RuneError> {
+        self.resolve(path, 0)
+    }
+
+    fn resolve(&self, path: &str, depth: usize) -> Result<Value, RuneError> {
+        let mut last_error = None;
+        for (_, config) in self.files.iter().rev() {
+            match config.get_value(path) {
+                // A reference into another file comes back unresolved
+                Ok(Value::Reference(parts)) if depth < MAX_REFERENCE_DEPTH => {
+                    return self.resolve(&parts.join("."), depth + 1);
+                }
+                Ok(value) => return Ok(value),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| RuneError::SyntaxError {
+            message: format!("Key '{}' not found", path),
+            line: 0,
+            column: 0,
+            hint: None,
+            code: Some(304),
+        }))
+    }
+
+    pub fn get<T>(&self, path: &str) -> Result<T, RuneError>
+    where
+        T: TryFrom<Value, Error = RuneError>,
+    {
+        T::try_from(self.get_value(path)?)
+    }
+
+    pub fn get_or<T>(&self, path: &str, default: T) -> T
+    where
+        T: TryFrom<Value, Error = RuneError>,
+    {
+        self.get(path).unwrap_or(default)
+    }
+
+    /// Keys of a block across every file, in first-seen order
+    pub fn get_keys(&self, path: &str) -> Result<Vec<String>, RuneError> {
+        let mut keys: Vec<String> = Vec::new();
+        let mut last_error = None;
+        for (_, config) in &self.files {
+            match config.get_keys(path) {
+                Ok(found) => {
+                    for key in found {
+                        if !keys.contains(&key) {
+                            keys.push(key);
+                        }
+                    }
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match (keys.is_empty(), last_error) {
+            (true, Some(e)) => Err(e),
+            _ => Ok(keys),
+        }
+    }
+}
+
+/// `conf.d` directory belonging to a main config file
+fn conf_dir(main: &Path) -> PathBuf {
+    main.parent().unwrap_or(Path::new(".")).join("conf.d")
+}
+
+/// Config files in `dir`, sorted so `10-theme.rune` loads before `20-laptop.rune`
+fn conf_d_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && config_source::is_config_file(path))
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn names(set: &ConfigSet, dir: &TempDir) -> Vec<String> {
+        set.files()
+            .map(|(path, _)| path.strip_prefix(dir.path()).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn includes_load_before_their_file_and_conf_d_last() {
+        let dir = TempDir::new("set-order");
+        let main = dir.write("snug.rune", "include \"a.rune\"\ninclude \"b.toml\"\n\nDP-1:\n  radius = 3\nend\n");
+        dir.write("a.rune", "include \"base.rune\"\n\nDP-1:\n  radius = 2\n  left = 2\nend\n");
+        dir.write("base.rune", "DP-1:\n  radius = 1\n  top = 1\nend\n");
+        dir.write("b.toml", "[DP-1]\nright = 6\n");
+        dir.write("conf.d/20-late.rune", "DP-1:\n  radius = 5\nend\n");
+        dir.write("conf.d/10-early.json", r#"{ "DP-1": { "radius": 4, "bottom": 4 } }"#);
+        // Sorted as text, not by number
+        dir.write("conf.d/9-last.rune", "DP-1:\n  bottom = 9\nend\n");
+        dir.write("conf.d/README.md", "not a config");
+
+        let set = ConfigSet::load(&main).unwrap();
+        assert_eq!(
+            names(&set, &dir),
+            ["base.rune", "a.rune", "b.toml", "snug.rune", "conf.d/10-early.json", "conf.d/20-late.rune", "conf.d/9-last.rune"]
+        );
+
+        // Each key comes from the last file setting it
+        assert_eq!(set.get::<f64>("DP-1.radius").unwrap(), 5.0);
+        assert_eq!(set.get::<f64>("DP-1.bottom").unwrap(), 9.0);
+        assert_eq!(set.get::<f64>("DP-1.left").unwrap(), 2.0);
+        assert_eq!(set.get::<f64>("DP-1.top").unwrap(), 1.0);
+        assert_eq!(set.get::<f64>("DP-1.right").unwrap(), 6.0);
+        assert!(set.get_value("DP-1.opacity").is_err());
+        assert_eq!(set.get_keys("DP-1").unwrap(), ["radius", "top", "left", "right", "bottom"]);
+    }
+
+    #[test]
+    fn sources_and_lines_point_at_the_winning_file() {
+        let dir = TempDir::new("set-source");
+        let main = dir.write("snug.rune", "include \"theme.rune\"\n\nDP-1:\n  radius = 3\n  color = \"161b22\"\nend\n");
+        let theme = dir.write("theme.rune", "DP-1:\n\n  left = 2\n  color = \"000000\"\nend\n");
+        let late = dir.write("conf.d/10-late.toml", "# laptop\n[DP-1]\nradius = 5\n");
+
+        let set = ConfigSet::load(&main).unwrap();
+        assert_eq!(set.source_of("DP-1.radius"), Some((late.as_path(), Some(3))));
+        assert_eq!(set.source_of("DP-1.color"), Some((main.as_path(), Some(5))));
+        assert_eq!(set.source_of("DP-1.left"), Some((theme.as_path(), Some(3))));
+        assert_eq!(set.source_of("DP-1.top"), None);
+
+        assert_eq!(set.line_of(&theme, "DP-1.color"), Some(4));
+        assert_eq!(set.line_of(&theme, "DP-1"), Some(1));
+        assert_eq!(set.line_of(&theme, "DP-1.radius"), None);
+        assert_eq!(set.line_of(&late, "DP-1"), Some(2));
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let dir = TempDir::new("set-cycle");
+        let main = dir.write("snug.rune", "include \"a.rune\"\n");
+        dir.write("a.rune", "include \"b.rune\"\n");
+        dir.write("b.rune", "include \"a.rune\"\n");
+        let error = ConfigSet::load(&main).err().unwrap().to_string();
+        assert!(error.contains("include cycle"), "{}", error);
+
+        let itself = dir.write("itself.rune", "include \"itself.rune\"\n");
+        let error = ConfigSet::load(&itself).err().unwrap().to_string();
+        assert!(error.contains("include cycle"), "{}", error);
+    }
+
+    #[test]
+    fn missing_includes_are_errors() {
+        let dir = TempDir::new("set-missing");
+        let main = dir.write("snug.rune", "include \"nowhere.rune\"\n\nDP-1:\n  radius = 3\nend\n");
+        let error = ConfigSet::load(&main).err().unwrap().to_string();
+        assert!(error.contains("included file not found"), "{}", error);
+        assert!(error.contains("nowhere.rune"), "{}", error);
+    }
+
+    #[test]
+    fn references_resolve_across_files() {
+        let dir = TempDir::new("set-reference");
+        let main = dir.write("snug.rune", "include \"theme.rune\"\n\nDP-1:\n  color = palette.bg\nend\n");
+        dir.write("theme.rune", "palette:\n  bg = \"161b22\"\nend\n");
+        let set = ConfigSet::load(&main).unwrap();
+        assert_eq!(set.get::<String>("DP-1.color").unwrap(), "161b22");
+    }
+}
@@ -0,0 +1,462 @@
+use eyre::{Result, eyre};
+use rune_cfg::{RuneConfig, RuneError, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// File extensions snug can load, in lookup order
+pub const EXTENSIONS: &[&str] = &["rune", "toml", "json"];
+
+/// One parsed config file, whatever its format
+///
+/// Every format is read into the same `rune_cfg::Value` tree, so lookups,
+/// `TryFrom` conversions and validation are shared. TOML and JSON files
+/// express `include` as a top-level string or list of strings.
+pub trait ConfigSource {
+    /// Top-level blocks and values, excluding `include`
+    fn items(&self) -> &[(String, Value)];
+
+    /// Files this one includes, relative to its own directory
+    fn includes(&self) -> Vec<String>;
+
+    /// Line of every `block.key` in `content`, for diagnostics
+    fn key_lines(&self, content: &str) -> HashMap<String, usize>;
+
+    /// Value at a dotted path such as `DP-1.radius`
+    fn get_value(&self, path: &str) -> Result<Value, RuneError> {
+        let mut parts = path.split('.');
+        let first = parts.next().unwrap_or_default();
+        let mut value = entry(self.items(), first).ok_or_else(|| not_found(path))?;
+        for part in parts {
+            let Value::Object(entries) = value else {
+                return Err(not_found(path));
+            };
+            value = entry(entries, part).ok_or_else(|| not_found(path))?;
+        }
+        Ok(value.clone())
+    }
+
+    /// Keys of the block at `path`
+    fn get_keys(&self, path: &str) -> Result<Vec<String>, RuneError> {
+        match self.get_value(path)? {
+            Value::Object(entries) => Ok(entries.into_iter().map(|(key, _)| key).collect()),
+            _ => Err(RuneError::TypeError {
+                message: format!("Path '{}' is not an object", path),
+                line: 0,
+                column: 0,
+                hint: None,
+                code: Some(306),
+            }),
+        }
+    }
+}
+
+/// Parse `path` with the loader matching its extension, RUNE by default
+pub fn load(path: &Path) -> Result<Box<dyn ConfigSource>> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => {
+            let content = read(path)?;
+            let table: toml::Table = toml::from_str(&content)
+                .map_err(|e| eyre!("Failed to parse TOML {}: {}", path.display(), e))?;
+            Ok(Box::new(TreeSource::new(from_toml(toml::Value::Table(table)), Format::Toml)))
+        }
+        Some("json") => {
+            let content = read(path)?;
+            let json: serde_json::Value = serde_json::from_str(&content)
+                .map_err(|e| eyre!("Failed to parse JSON {}: {}", path.display(), e))?;
+            if !json.is_object() {
+                return Err(eyre!("{}: the top level must be an object", path.display()));
+            }
+            Ok(Box::new(TreeSource::new(from_json(json), Format::Json)))
+        }
+        _ => {
+            let config = RuneConfig::from_file(path)
+                .map_err(|e| eyre!("Failed to load config {}: {}", path.display(), e))?;
+            Ok(Box::new(config))
+        }
+    }
+}
+
+/// Whether `path` has an extension one of the loaders understands
+pub fn is_config_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext))
+}
+
+fn read(path: &Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|e| eyre!("Failed to read {}: {}", path.display(), e))
+}
+
+fn entry<'a>(entries: &'a [(String, Value)], name: &str) -> Option<&'a Value> {
+    entries.iter().find(|(key, _)| key == name).map(|(_, value)| value)
+}
+
+fn not_found(path: &str) -> RuneError {
+    RuneError::SyntaxError {
+        message: format!("Key '{}' not found", path),
+        line: 0,
+        column: 0,
+        hint: None,
+        code: Some(304),
+    }
+}
+
+impl ConfigSource for RuneConfig {
+    fn items(&self) -> &[(String, Value)] {
+        self.document().map_or(&[], |doc| doc.items.as_slice())
+    }
+
+    fn includes(&self) -> Vec<String> {
+        let Some(document) = self.document() else {
+            return Vec::new();
+        };
+        document
+            .globals
+            .iter()
+            .filter(|(name, _)| name == "include")
+            .filter_map(|(_, value)| match value {
+                Value::String(path) => Some(path.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn key_lines(&self, content: &str) -> HashMap<String, usize> {
+        let mut lines = HashMap::new();
+        let mut scope: Vec<String> = Vec::new();
+        for (idx, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('@') {
+                continue;
+            }
+            if trimmed == "end" {
+                scope.pop();
+                continue;
+            }
+            if let Some(block) = trimmed.strip_suffix(':') {
+                scope.push(block.trim().to_string());
+                lines.entry(scope.join(".")).or_insert(idx + 1);
+                continue;
+            }
+            if let Some((key, _)) = trimmed.split_once('=') {
+                let mut path = scope.clone();
+                path.push(key.trim().to_string());
+                lines.entry(path.join(".")).or_insert(idx + 1);
+            }
+        }
+        lines
+    }
+
+    // RUNE resolves its own references, so use its lookups rather than the tree walk
+    fn get_value(&self, path: &str) -> Result<Value, RuneError> {
+        RuneConfig::get_value(self, path)
+    }
+
+    fn get_keys(&self, path: &str) -> Result<Vec<String>, RuneError> {
+        RuneConfig::get_keys(self, path)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Toml,
+    Json,
+}
+
+/// A TOML or JSON document converted to RUNE values
+struct TreeSource {
+    items: Vec<(String, Value)>,
+    includes: Vec<String>,
+    format: Format,
+}
+
+impl TreeSource {
+    fn new(root: Value, format: Format) -> Self {
+        let Value::Object(entries) = root else {
+            return Self { items: Vec::new(), includes: Vec::new(), format };
+        };
+        let mut items = Vec::new();
+        let mut includes = Vec::new();
+        for (name, value) in entries {
+            if name != "include" {
+                items.push((name, value));
+                continue;
+            }
+            match value {
+                Value::String(path) => includes.push(path),
+                Value::Array(paths) => includes.extend(paths.into_iter().filter_map(|path| match path {
+                    Value::String(path) => Some(path),
+                    _ => None,
+                })),
+                _ => {}
+            }
+        }
+        Self { items, includes, format }
+    }
+}
+
+impl ConfigSource for TreeSource {
+    fn items(&self) -> &[(String, Value)] {
+        &self.items
+    }
+
+    fn includes(&self) -> Vec<String> {
+        self.includes.clone()
+    }
+
+    fn key_lines(&self, content: &str) -> HashMap<String, usize> {
+        match self.format {
+            Format::Toml => toml_key_lines(content),
+            Format::Json => json_key_lines(content),
+        }
+    }
+}
+
+/// `[table]` headers and `key = value` lines
+fn toml_key_lines(content: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    let mut table = String::new();
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = trimmed.strip_prefix('[').and_then(|h| h.split(']').next()) {
+            table = header.trim().trim_matches('"').to_string();
+            lines.entry(table.clone()).or_insert(idx + 1);
+            continue;
+        }
+        if let Some((key, _)) = trimmed.split_once('=') {
+            let key = key.trim().trim_matches('"');
+            let path = if table.is_empty() { key.to_string() } else { format!("{}.{}", table, key) };
+            lines.entry(path).or_insert(idx + 1);
+        }
+    }
+    lines
+}
+
+/// `"key": value` lines, tracking which objects they are nested in
+fn json_key_lines(content: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    let mut scope: Vec<Option<String>> = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        let mut opened_key = None;
+        if let Some(rest) = trimmed.strip_prefix('"')
+            && let Some((key, after)) = rest.split_once("\":")
+        {
+            let mut path: Vec<&str> = scope.iter().flatten().map(String::as_str).collect();
+            path.push(key);
+            lines.entry(path.join(".")).or_insert(idx + 1);
+            if after.trim_start().starts_with('{') {
+                opened_key = Some(key.to_string());
+            }
+        }
+        for c in trimmed.chars() {
+            match c {
+                '{' => scope.push(opened_key.take()),
+                '}' => {
+                    scope.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    lines
+}
+
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Number(i as f64),
+        toml::Value::Float(f) => Value::Number(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect()),
+    }
+}
+
+fn from_json(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or_default()),
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(items) => Value::Array(items.into_iter().map(from_json).collect()),
+        serde_json::Value::Object(map) => Value::Object(map.into_iter().map(|(k, v)| (k, from_json(v))).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_config_silent, SnugConfig};
+    use crate::test_util::TempDir;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    const RUNE: &str = r#"
+palette:
+  bg = "161b22"
+end
+
+hyprland:
+  enabled = false
+  sync_gaps = true
+end
+
+indicator:
+  interval = "10s"
+end
+
+DP-1:
+  radius = 15
+  left = 4
+  color = "bg"
+  opacity = 0.85
+  shadow_enabled = true
+  shadow_blur = 12.5
+  workspace_colors = ["1:ff0000", "special:magic:3b2e4a"]
+end
+
+HDMI-A-1:
+  radius = 0
+  top = 2
+  color = "rgb(10, 20, 30)"
+  hide_on_fullscreen = false
+end
+
+profiles:
+  night:
+    DP-1:
+      color = "1a1008"
+      radius = 0
+    end
+  end
+end
+
+schedule:
+  times = ["07:30", "22:00 night"]
+  fade = "30m"
+end
+"#;
+
+    const TOML: &str = r#"
+[palette]
+bg = "161b22"
+
+[hyprland]
+enabled = false
+sync_gaps = true
+
+[indicator]
+interval = "10s"
+
+[DP-1]
+radius = 15
+left = 4
+color = "bg"
+opacity = 0.85
+shadow_enabled = true
+shadow_blur = 12.5
+workspace_colors = ["1:ff0000", "special:magic:3b2e4a"]
+
+[HDMI-A-1]
+radius = 0
+top = 2
+color = "rgb(10, 20, 30)"
+hide_on_fullscreen = false
+
+[profiles.night.DP-1]
+color = "1a1008"
+radius = 0
+
+[schedule]
+times = ["07:30", "22:00 night"]
+fade = "30m"
+"#;
+
+    const JSON: &str = r#"{
+  "palette": { "bg": "161b22" },
+  "hyprland": { "enabled": false, "sync_gaps": true },
+  "indicator": { "interval": "10s" },
+  "DP-1": {
+    "radius": 15,
+    "left": 4,
+    "color": "bg",
+    "opacity": 0.85,
+    "shadow_enabled": true,
+    "shadow_blur": 12.5,
+    "workspace_colors": ["1:ff0000", "special:magic:3b2e4a"]
+  },
+  "HDMI-A-1": { "radius": 0, "top": 2, "color": "rgb(10, 20, 30)", "hide_on_fullscreen": false },
+  "profiles": { "night": { "DP-1": { "color": "1a1008", "radius": 0 } } },
+  "schedule": { "times": ["07:30", "22:00 night"], "fade": "30m" }
+}"#;
+
+    /// Everything loaded, in a form that compares without caring about map order
+    fn snapshot(config: &SnugConfig) -> String {
+        let displays: BTreeMap<_, _> = config.displays.iter().collect();
+        let profiles: BTreeMap<_, BTreeMap<_, _>> = config
+            .profiles
+            .iter()
+            .map(|(name, displays)| (name, displays.iter().collect()))
+            .collect();
+        format!(
+            "{}\n{}\n{}\n{}\n{:?}",
+            serde_json::to_string(&displays).unwrap(),
+            serde_json::to_string(&profiles).unwrap(),
+            serde_json::to_string(&config.hyprland).unwrap(),
+            serde_json::to_string(&config.indicator).unwrap(),
+            config.schedule,
+        )
+    }
+
+    #[test]
+    fn formats_load_into_the_same_config() {
+        let dir = TempDir::new("formats");
+        let [rune, toml, json] = [("snug.rune", RUNE), ("snug.toml", TOML), ("snug.json", JSON)]
+            .map(|(name, contents)| snapshot(&load_config_silent(&dir.write(name, contents)).unwrap()));
+
+        assert_eq!(rune, toml);
+        assert_eq!(rune, json);
+
+        let config = load_config_silent(&dir.path().join("snug.json")).unwrap();
+        let display = config.get_display_config("DP-1");
+        assert_eq!((display.radius, display.left, display.color.as_str()), (15, 4, "161b22ff"));
+        assert_eq!(display.opacity, Some(0.85));
+        assert_eq!(config.display_config_for(Some("night"), "DP-1").color, "1a1008");
+        assert!(!config.hyprland.enabled && config.hyprland.sync_gaps);
+        assert_eq!(config.indicator.interval, Duration::from_secs(10));
+        assert!(config.schedule.is_some());
+    }
+
+    #[test]
+    fn tree_lookups_walk_nested_blocks() {
+        let dir = TempDir::new("tree");
+        for (name, contents) in [("snug.toml", TOML), ("snug.json", JSON)] {
+            let source = load(&dir.write(name, contents)).unwrap();
+            assert!(matches!(source.get_value("profiles.night.DP-1.radius"), Ok(Value::Number(n)) if n == 0.0));
+            assert!(matches!(source.get_value("DP-1.shadow_enabled"), Ok(Value::Bool(true))));
+            assert!(matches!(source.get_value("DP-1.workspace_colors"), Ok(Value::Array(items)) if items.len() == 2));
+            assert!(source.get_value("DP-1.radius.deeper").is_err());
+            assert!(source.get_value("DP-2").is_err());
+
+            let mut keys = source.get_keys("profiles.night.DP-1").unwrap();
+            keys.sort();
+            assert_eq!(keys, ["color", "radius"]);
+        }
+    }
+
+    #[test]
+    fn includes_are_read_from_every_format() {
+        let dir = TempDir::new("includes");
+        let toml = load(&dir.write("a.toml", "include = \"theme.rune\"\n")).unwrap();
+        assert_eq!(toml.includes(), ["theme.rune"]);
+        let json = load(&dir.write("a.json", r#"{ "include": ["one.toml", "two.json"] }"#)).unwrap();
+        assert_eq!(json.includes(), ["one.toml", "two.json"]);
+        assert!(json.items().is_empty());
+    }
+}
@@ -1,15 +1,81 @@
 use crate::args::MergedConfig;
+use crate::colour::{linear_to_srgb, Colour};
 
-/// Parse hex color string to RGB
-fn parse_hex_color(hex: &str) -> (u8, u8, u8) {
-    let hex = hex.trim_start_matches('#');
-    if hex.len() == 6 {
-        let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-        let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-        let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-        (r, g, b)
-    } else {
-        (0, 0, 0)
+/// Colour space used to composite anti-aliased edges and the shadow
+///
+/// The pipeline is the same in both: every colour becomes premultiplied
+/// (r, g, b, a) in the working space, pixels are a coverage-weighted sum of
+/// those, and the result is encoded back to premultiplied sRGB for `wl_shm`.
+/// `Linear` does the weighting in linear light, which keeps edges from
+/// looking thin and dark; `Srgb` weights the encoded values directly, which
+/// is how snug has always drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendSpace {
+    Linear,
+    Srgb,
+}
+
+impl BlendSpace {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "linear" => Some(Self::Linear),
+            "srgb" | "legacy" => Some(Self::Srgb),
+            _ => None,
+        }
+    }
+}
+
+/// A premultiplied pixel in the working blend space
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Premul {
+    r: f64,
+    g: f64,
+    b: f64,
+    a: f64,
+}
+
+impl Premul {
+    const CLEAR: Self = Self { r: 0.0, g: 0.0, b: 0.0, a: 0.0 };
+
+    fn new(colour: Colour, space: BlendSpace) -> Self {
+        let (r, g, b, a) = match space {
+            BlendSpace::Linear => {
+                let linear = colour.to_linear();
+                (linear.r, linear.g, linear.b, linear.a)
+            }
+            BlendSpace::Srgb => (colour.r, colour.g, colour.b, colour.a),
+        };
+        Self { r: r * a, g: g * a, b: b * a, a }
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        Self { r: self.r * factor, g: self.g * factor, b: self.b * factor, a: self.a * factor }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self { r: self.r + other.r, g: self.g + other.g, b: self.b + other.b, a: self.a + other.a }
+    }
+
+    /// Encode as premultiplied sRGB in ARGB8888 byte order (B, G, R, A)
+    ///
+    /// Linear values are un-premultiplied only to apply the transfer
+    /// function, then premultiplied again by the same alpha.
+    fn encode(self, space: BlendSpace) -> [u8; 4] {
+        let a = self.a.clamp(0.0, 1.0);
+        if a <= 0.0 {
+            return [0; 4];
+        }
+        let (r, g, b) = match space {
+            BlendSpace::Linear => (
+                linear_to_srgb(self.r / a) * a,
+                linear_to_srgb(self.g / a) * a,
+                linear_to_srgb(self.b / a) * a,
+            ),
+            BlendSpace::Srgb => (self.r, self.g, self.b),
+        };
+        // Premultiplied channels can never exceed alpha
+        let byte = |v: f64| (v.clamp(0.0, a) * 255.0).round() as u8;
+        [byte(b), byte(g), byte(r), byte(a)]
     }
 }
 
@@ -36,10 +102,7 @@ pub fn draw_snug(
     canvas: &mut [u8],
     width: i32,
     height: i32,
-    r: u8,
-    g: u8,
-    b: u8,
-    a: u8,
+    colour: Colour,
     config: &MergedConfig
 ) {
     let w = width as f64;
@@ -47,11 +110,7 @@ pub fn draw_snug(
     let radius = config.radius as f64;
     
     // premultiplied color
-    let af = a as f32 / 255.0;
-    let pr = (r as f32 * af).round() as u8;
-    let pg = (g as f32 * af).round() as u8;
-    let pb = (b as f32 * af).round() as u8;
-    let pa = a;
+    let [pb, pg, pr, pa] = colour.to_argb8888();
     
     // Fill background with premultiplied color
     for chunk in canvas.chunks_exact_mut(4) {
@@ -75,14 +134,17 @@ pub fn draw_snug(
     }
     
     let aa = 1.0_f64;
+    let space = config.blend.as_deref().and_then(BlendSpace::parse).unwrap_or(BlendSpace::Linear);
+    let frame = Premul::new(colour, space);
     
     // Get shadow config with clamping
     let shadow_enabled = config.shadow_enabled.unwrap_or(false);
-    let shadow_color_str = config.shadow_color.as_ref()
-        .map(|s| s.as_str())
-        .unwrap_or("000000");
-    let (sr, sg, sb) = parse_hex_color(shadow_color_str);
-    let shadow_opacity = config.shadow_opacity.unwrap_or(0.5).clamp(0.0, 1.0);
+    let shadow_colour = config.shadow_color.as_deref()
+        .and_then(|s| Colour::parse(s).ok())
+        .unwrap_or(Colour::BLACK);
+    // The colour's own alpha scales the configured shadow opacity
+    let shadow_opacity = config.shadow_opacity.unwrap_or(0.5).clamp(0.0, 1.0) * shadow_colour.a;
+    let shadow = Premul::new(shadow_colour.with_alpha(shadow_opacity), space);
     
     // Clamp shadow_blur: config value is 0.0-1.0, map to 1.0-15.0 pixels
     let shadow_blur_config = config.shadow_blur.unwrap_or(0.5).clamp(0.0, 1.0);
@@ -115,83 +177,132 @@ pub fn draw_snug(
             let dist = (dx * dx + dy * dy).sqrt();
             let drr = dist - radius;
             
-            if drr <= -aa {
-                // Inside the cutout
-                if shadow_enabled {
-                    // Distance from the inner edge (positive = inside, away from edge)
-                    let inner_dist = -drr;
-                    
-                    // Only draw shadow within blur radius
-                    if inner_dist <= shadow_blur {
-                        let falloff = shadow_falloff(inner_dist, shadow_blur);
-                        let shadow_strength = shadow_opacity * falloff;
-                        
-                        if shadow_strength > 0.001 {
-                            let sa = (shadow_strength as f32).min(1.0);
-                            
-                            // Premultiply shadow color
-                            let sr_pm = (sr as f32 / 255.0) * sa;
-                            let sg_pm = (sg as f32 / 255.0) * sa;
-                            let sb_pm = (sb as f32 / 255.0) * sa;
-                            
-                            canvas[idx] = (sb_pm * 255.0).round() as u8;
-                            canvas[idx + 1] = (sg_pm * 255.0).round() as u8;
-                            canvas[idx + 2] = (sr_pm * 255.0).round() as u8;
-                            canvas[idx + 3] = (sa * 255.0).round() as u8;
-                            continue;
-                        }
-                    }
-                }
-                
-                // No shadow or outside shadow range
+            if drr >= aa {
+                // Fully inside the border, keep the background fill
+                continue;
+            }
+            
+            // Fraction of the pixel covered by the border: 0 in the cutout,
+            // ramping to 1 across the AA band
+            let coverage = ((drr + aa) / (2.0 * aa)).clamp(0.0, 1.0);
+            
+            // Shadow strength for the uncovered part, full strength at the edge
+            let shadow_strength = if shadow_enabled {
+                shadow_falloff(-drr, shadow_blur)
+            } else {
+                0.0
+            };
+            
+            // Coverage-weighted sum of premultiplied border and shadow
+            let pixel = frame.scale(coverage).add(shadow.scale(shadow_strength * (1.0 - coverage)));
+            if pixel == Premul::CLEAR {
                 canvas[idx..idx + 4].fill(0);
-                
-            } else if drr < aa {
-                // AA band at the border edge
-                let t = (drr + aa) / (2.0 * aa);
-                let coverage = 1.0 - t.clamp(0.0, 1.0);
-                
-                if shadow_enabled && coverage > 0.001 {
-                    // At the edge, blend shadow with border
-                    let shadow_strength = shadow_opacity;
-                    let sa = (shadow_strength as f32 * coverage as f32).min(1.0);
-                    
-                    let sr_pm = (sr as f32 / 255.0) * sa;
-                    let sg_pm = (sg as f32 / 255.0) * sa;
-                    let sb_pm = (sb as f32 / 255.0) * sa;
-                    
-                    let border_factor = (1.0 - coverage) as f32;
-                    let border_a = (pa as f32 / 255.0) * border_factor;
-                    
-                    let out_alpha = sa + border_a;
-                    
-                    if out_alpha > 0.001 {
-                        let out_r_pm = sr_pm + (pr as f32 / 255.0) * border_factor;
-                        let out_g_pm = sg_pm + (pg as f32 / 255.0) * border_factor;
-                        let out_b_pm = sb_pm + (pb as f32 / 255.0) * border_factor;
-                        
-                        canvas[idx] = (out_b_pm * 255.0).round() as u8;
-                        canvas[idx + 1] = (out_g_pm * 255.0).round() as u8;
-                        canvas[idx + 2] = (out_r_pm * 255.0).round() as u8;
-                        canvas[idx + 3] = (out_alpha * 255.0).round() as u8;
-                    } else {
-                        canvas[idx..idx + 4].fill(0);
-                    }
-                } else {
-                    // No shadow - original AA
-                    let out_alpha = (1.0 - coverage) * (pa as f64 / 255.0);
-                    if out_alpha <= 0.0 {
-                        canvas[idx..idx + 4].fill(0);
-                    } else {
-                        let out_a_u8 = (out_alpha * 255.0).round() as u8;
-                        let out_r = ((pr as f32) * (out_alpha as f32 / (pa as f32 / 255.0))).round() as u8;
-                        let out_g = ((pg as f32) * (out_alpha as f32 / (pa as f32 / 255.0))).round() as u8;
-                        let out_b = ((pb as f32) * (out_alpha as f32 / (pa as f32 / 255.0))).round() as u8;
-                        canvas[idx..idx + 4].copy_from_slice(&[out_b, out_g, out_r, out_a_u8]);
-                    }
-                }
+            } else {
+                canvas[idx..idx + 4].copy_from_slice(&pixel.encode(space));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(radius: i32, border: i32) -> MergedConfig {
+        MergedConfig {
+            radius,
+            left: border,
+            right: border,
+            top: border,
+            bottom: border,
+            color: String::new(),
+            opacity: None,
+            shadow_enabled: None,
+            shadow_color: None,
+            shadow_opacity: None,
+            shadow_blur: None,
+            blend: None,
+        }
+    }
+
+    fn render(width: i32, height: i32, colour: &str, config: &MergedConfig) -> Vec<[u8; 4]> {
+        let mut canvas = vec![0xaa; (width * height * 4) as usize];
+        draw_snug(&mut canvas, width, height, Colour::parse(colour).unwrap(), config);
+        canvas.chunks_exact(4).map(|pixel| pixel.try_into().unwrap()).collect()
+    }
+
+    #[test]
+    fn square_frame_is_solid_with_a_clear_opening() {
+        let mut config = config(0, 1);
+        config.right = 2;
+        let pixels = render(6, 5, "ff800080", &config);
+        let frame = [0x00, 0x40, 0x80, 0x80];
+        for y in 0..5 {
+            for x in 0..6 {
+                let inside = (1..4).contains(&x) && (1..4).contains(&y);
+                let expected = if inside { [0; 4] } else { frame };
+                assert_eq!(pixels[y * 6 + x], expected, "pixel ({}, {})", x, y);
             }
-            // else: outside border, keep as-is
         }
     }
+
+    #[test]
+    fn rounded_corner_edges_are_antialiased() {
+        // Every border pixel is inside the 1px band around the rounded opening
+        let pixels = render(10, 10, "ffffff", &config(2, 2));
+        let at = |x: usize, y: usize| pixels[y * 10 + x];
+        // Corner: 2.12px from the inner rect, 0.56 covered
+        assert_eq!(at(0, 0), [143, 143, 143, 143]);
+        assert_eq!(at(9, 9), [143, 143, 143, 143]);
+        // Next to the corner: 1.58px, 0.29 covered
+        assert_eq!(at(1, 0), [74, 74, 74, 74]);
+        assert_eq!(at(0, 1), [74, 74, 74, 74]);
+        // Straight edges: 1.5px, a quarter covered
+        assert_eq!(at(0, 5), [64, 64, 64, 64]);
+        assert_eq!(at(5, 9), [64, 64, 64, 64]);
+        // Opening
+        assert_eq!(at(1, 1), [0; 4]);
+        assert_eq!(at(5, 5), [0; 4]);
+    }
+
+    #[test]
+    fn partial_pixels_match_in_both_blend_spaces() {
+        let mut srgb = config(2, 2);
+        srgb.blend = Some("srgb".into());
+        let pixels = render(10, 10, "808080", &srgb);
+        assert_eq!(pixels[0], [72, 72, 72, 143]);
+        assert_eq!(pixels[5], [32, 32, 32, 64]);
+
+        // Without a shadow, edges only mix the frame with clear, which
+        // comes out the same in both spaces
+        let linear = render(10, 10, "808080", &config(2, 2));
+        assert_eq!(linear, pixels);
+    }
+
+    #[test]
+    fn shadow_fills_the_uncovered_part_of_edge_pixels() {
+        let mut config = config(2, 2);
+        config.shadow_enabled = Some(true);
+        config.shadow_blur = Some(0.0);
+        let pixels = render(10, 10, "ff0000", &config);
+        // 0.56 red over half-opacity black, mixed in linear light
+        assert_eq!(pixels[0], [0, 0, 172, 199]);
+        assert_eq!(pixels[1], [0, 0, 97, 121]);
+        assert_eq!(pixels[5], [0, 0, 84, 104]);
+        assert_eq!(pixels[55], [0; 4]);
+    }
+
+    #[test]
+    fn encode_premultiplies_after_the_transfer_function() {
+        assert_eq!(Premul::CLEAR.encode(BlendSpace::Linear), [0; 4]);
+        let white = Premul::new(Colour::WHITE, BlendSpace::Linear).scale(0.4);
+        assert_eq!(white.encode(BlendSpace::Linear), [102, 102, 102, 102]);
+        // 50% linear grey is 188 once encoded
+        let grey = Premul { r: 0.5, g: 0.5, b: 0.5, a: 1.0 };
+        assert_eq!(grey.encode(BlendSpace::Linear), [188, 188, 188, 255]);
+        assert_eq!(grey.encode(BlendSpace::Srgb), [128, 128, 128, 255]);
+        // Rounding error never lets a channel exceed alpha
+        let over = Premul { r: 0.6, g: 0.0, b: 0.0, a: 0.5 };
+        assert_eq!(over.encode(BlendSpace::Srgb), [0, 0, 128, 128]);
+    }
 }
@@ -0,0 +1,283 @@
+use crate::args::{Args, DumpFormat, MergedConfig};
+use crate::config::{load_config_silent, requested_profile, SnugConfig};
+use crate::config_set::ConfigSet;
+use crate::paths::locate_config;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Fields of `MergedConfig` in the order they are printed
+const FIELDS: &[&str] = &[
+    "radius",
+    "left",
+    "right",
+    "top",
+    "bottom",
+    "color",
+    "opacity",
+    "shadow_enabled",
+    "shadow_color",
+    "shadow_opacity",
+    "shadow_blur",
+    "blend",
+];
+
+/// Print the merged config each display would use, with where every value came from
+pub fn run(args: &Args, display: Option<&str>, format: DumpFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let location = locate_config(args.config.as_deref());
+    let (mut config, set) = if location.path.is_file() {
+        (load_config_silent(&location.path)?, Some(ConfigSet::load(&location.path)?))
+    } else {
+        (SnugConfig::default(), None)
+    };
+    config.set_profile(requested_profile(args).as_deref())?;
+
+    let names: Vec<&str> = match display {
+        Some(name) => vec![name],
+        None if config.display_names().is_empty() => vec!["default"],
+        None => config.display_names(),
+    };
+
+    let profile = config.active_profile.as_deref();
+    let displays: Vec<(&str, Vec<Entry>)> = names
+        .into_iter()
+        .map(|name| {
+            let merged = args.merge_with_config(&config.get_display_config(name));
+            (name, entries(args, name, &merged, set.as_ref(), profile))
+        })
+        .collect();
+
+    match format {
+        DumpFormat::Rune => print!("{}", render_rune(&location.path, profile, &displays)),
+        DumpFormat::Toml => print!("{}", render_toml(&location.path, profile, &displays)),
+        DumpFormat::Json => println!("{}", serde_json::to_string_pretty(&render_json(&displays))?),
+    }
+    Ok(())
+}
+
+/// One merged value and its provenance
+struct Entry {
+    key: &'static str,
+    value: Value,
+    source: String,
+}
+
+fn entries(args: &Args, display: &str, merged: &MergedConfig, set: Option<&ConfigSet>, profile: Option<&str>) -> Vec<Entry> {
+    let Ok(Value::Object(mut values)) = serde_json::to_value(merged) else {
+        return Vec::new();
+    };
+    FIELDS
+        .iter()
+        .map(|&key| Entry {
+            key,
+            value: values.remove(key).unwrap_or(Value::Null),
+            source: source(args, display, key, set, profile),
+        })
+        .collect()
+}
+
+/// Where a merged value came from: a CLI flag, a config file line, or the default
+fn source(args: &Args, display: &str, key: &str, set: Option<&ConfigSet>, profile: Option<&str>) -> String {
+    let from_cli = match key {
+        "radius" => args.radius.is_some(),
+        "left" => args.left.is_some(),
+        "right" => args.right.is_some(),
+        "top" => args.top.is_some(),
+        "bottom" => args.bottom.is_some(),
+        "color" => args.color.is_some(),
+        "opacity" => args.opacity.is_some(),
+        "shadow_enabled" => args.shadow_enabled.is_some(),
+        "shadow_color" => args.shadow_color.is_some(),
+        "shadow_opacity" => args.shadow_opacity.is_some(),
+        "shadow_blur" => args.shadow_blur.is_some(),
+        "blend" => args.blend.is_some(),
+        _ => false,
+    };
+    if from_cli {
+        return format!("--{}", key);
+    }
+
+    // The active profile's block wins over the base block
+    let paths = profile
+        .map(|profile| format!("profiles.{}.{}.{}", profile, display, key))
+        .into_iter()
+        .chain([format!("{}.{}", display, key)]);
+    let found = set.and_then(|set| paths.into_iter().find_map(|path| set.source_of(&path)));
+    match found {
+        Some((file, Some(line))) => format!("{}:{}", file.display(), line),
+        Some((file, None)) => file.display().to_string(),
+        None => "default".to_string(),
+    }
+}
+
+/// Scalars print the same way in RUNE, TOML and JSON
+fn scalar(value: &Value) -> String {
+    value.to_string()
+}
+
+/// Comment naming the file, and the profile if one is active
+fn header(path: &Path, profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("# Effective configuration, loaded from {}, profile {}\n", path.display(), profile),
+        None => format!("# Effective configuration, loaded from {}\n", path.display()),
+    }
+}
+
+fn render_rune(path: &Path, profile: Option<&str>, displays: &[(&str, Vec<Entry>)]) -> String {
+    let mut out = header(path, profile);
+    for (name, entries) in displays {
+        out.push_str(&format!("\n{}:\n", name));
+        for entry in entries {
+            if entry.value.is_null() {
+                out.push_str(&format!("  # {} is not set ({})\n", entry.key, entry.source));
+            } else {
+                let line = format!("  {} = {}", entry.key, scalar(&entry.value));
+                out.push_str(&format!("{:<36} # {}\n", line, entry.source));
+            }
+        }
+        out.push_str("end\n");
+    }
+    out
+}
+
+fn render_toml(path: &Path, profile: Option<&str>, displays: &[(&str, Vec<Entry>)]) -> String {
+    let mut out = header(path, profile);
+    for (name, entries) in displays {
+        out.push_str(&format!("\n[{}]\n", name));
+        for entry in entries {
+            // TOML has no null, unset values stay comments
+            if entry.value.is_null() {
+                out.push_str(&format!("# {} is not set ({})\n", entry.key, entry.source));
+            } else {
+                let line = format!("{} = {}", entry.key, scalar(&entry.value));
+                out.push_str(&format!("{:<34} # {}\n", line, entry.source));
+            }
+        }
+    }
+    out
+}
+
+fn render_json(displays: &[(&str, Vec<Entry>)]) -> Value {
+    let mut root = Map::new();
+    for (name, entries) in displays {
+        let mut values = Map::new();
+        let mut sources = Map::new();
+        for entry in entries {
+            values.insert(entry.key.to_string(), entry.value.clone());
+            sources.insert(entry.key.to_string(), Value::String(entry.source.clone()));
+        }
+        let mut display = Map::new();
+        display.insert("config".to_string(), Value::Object(values));
+        display.insert("sources".to_string(), Value::Object(sources));
+        root.insert(name.to_string(), Value::Object(display));
+    }
+    Value::Object(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    const MAIN: &str = "DP-1:
+  radius = 15
+  color = \"161b22\"
+end
+
+profiles:
+  night:
+    DP-1:
+      color = \"000000\"
+    end
+  end
+end
+";
+
+    struct Fixture {
+        dir: TempDir,
+        config: SnugConfig,
+        set: ConfigSet,
+        args: Args,
+    }
+
+    /// A main file, a conf.d fragment setting `left`, and `--top 7` on the command line
+    fn fixture() -> Fixture {
+        let dir = TempDir::new("dump");
+        let main = dir.write("snug.rune", MAIN);
+        dir.write("conf.d/10-left.rune", "DP-1:\n  left = 4\nend\n");
+        Fixture {
+            config: load_config_silent(&main).unwrap(),
+            set: ConfigSet::load(&main).unwrap(),
+            args: Args { top: Some(7), ..Args::default() },
+            dir,
+        }
+    }
+
+    impl Fixture {
+        fn entries(&self, profile: Option<&str>) -> Vec<Entry> {
+            let merged = self.args.merge_with_config(&self.config.display_config_for(profile, "DP-1"));
+            entries(&self.args, "DP-1", &merged, Some(&self.set), profile)
+        }
+
+        fn main(&self) -> String {
+            self.dir.path().join("snug.rune").display().to_string()
+        }
+    }
+
+    fn find<'a>(entries: &'a [Entry], key: &str) -> &'a Entry {
+        entries.iter().find(|entry| entry.key == key).unwrap()
+    }
+
+    #[test]
+    fn values_carry_their_source() {
+        let fixture = fixture();
+        let entries = fixture.entries(None);
+        assert_eq!(entries.iter().map(|entry| entry.key).collect::<Vec<_>>(), FIELDS);
+
+        let radius = find(&entries, "radius");
+        assert_eq!((&radius.value, radius.source.as_str()), (&Value::from(15), format!("{}:2", fixture.main()).as_str()));
+        let left = find(&entries, "left");
+        assert_eq!(left.value, Value::from(4));
+        assert!(left.source.ends_with("conf.d/10-left.rune:2"), "{}", left.source);
+        let top = find(&entries, "top");
+        assert_eq!((&top.value, top.source.as_str()), (&Value::from(7), "--top"));
+        let shadow = find(&entries, "shadow_color");
+        assert_eq!((&shadow.value, shadow.source.as_str()), (&Value::Null, "default"));
+    }
+
+    #[test]
+    fn profile_values_point_at_the_profile_block() {
+        let fixture = fixture();
+        let entries = fixture.entries(Some("night"));
+        let color = find(&entries, "color");
+        assert!(color.value.as_str().unwrap().starts_with("000000"), "{}", color.value);
+        assert_eq!(color.source, format!("{}:9", fixture.main()));
+        assert_eq!(find(&entries, "radius").source, format!("{}:2", fixture.main()));
+    }
+
+    #[test]
+    fn every_format_shows_values_and_sources() {
+        let fixture = fixture();
+        let displays = vec![("DP-1", fixture.entries(None))];
+        let path = fixture.dir.path().join("snug.rune");
+        let source = format!("# {}:2", fixture.main());
+
+        let rune = render_rune(&path, Some("night"), &displays);
+        assert!(rune.starts_with(&format!("# Effective configuration, loaded from {}, profile night\n", fixture.main())));
+        assert!(rune.contains("\nDP-1:\n"));
+        assert!(rune.lines().any(|line| line.starts_with("  radius = 15 ") && line.ends_with(&source)), "{}", rune);
+        assert!(rune.contains("  top = 7"));
+        assert!(rune.contains("  # shadow_color is not set (default)\n"));
+        assert!(rune.ends_with("end\n"));
+
+        let toml = render_toml(&path, None, &displays);
+        assert!(toml.contains("\n[DP-1]\n"));
+        assert!(toml.lines().any(|line| line.starts_with("radius = 15 ") && line.ends_with(&source)), "{}", toml);
+        assert!(toml.contains("# shadow_color is not set (default)\n"));
+
+        let json = render_json(&displays);
+        assert_eq!(json["DP-1"]["config"]["radius"], 15);
+        assert_eq!(json["DP-1"]["config"]["shadow_color"], Value::Null);
+        assert_eq!(json["DP-1"]["sources"]["top"], "--top");
+        assert_eq!(json["DP-1"]["sources"]["radius"], format!("{}:2", fixture.main()));
+    }
+}
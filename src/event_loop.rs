@@ -1,8 +1,18 @@
+use crate::animation::Animation;
 use crate::app::App;
-use crate::args::Args;
-use crate::config::{load_config_silent, load_config_or_default};
+use crate::args::{Args, MsgAction};
+use crate::config::{load_config_silent, load_config_or_default, reload_config, requested_profile, DisplayConfig, SnugConfig};
+use crate::external::ExternalSource;
+use crate::indicator::IndicatorDriver;
+use crate::integrations::{self, CompositorEvent, CompositorStatus};
+use crate::ipc;
+use crate::notify::{self, Watchdog};
+use crate::overlay::RuntimeOverlay;
+use crate::power::OutputPower;
+use crate::watcher;
 use crate::wayland;
 use crate::process::release_lock;
+use crate::schedule::ScheduleDriver;
 use smithay_client_toolkit::{
     compositor::CompositorState,
     output::OutputState,
@@ -11,12 +21,18 @@ use smithay_client_toolkit::{
     shell::{wlr_layer::LayerShell, WaylandSurface},
     shm::Shm,
 };
-use wayland_client::{globals::registry_queue_init, Proxy};
+use wayland_client::{globals::registry_queue_init, Proxy, QueueHandle};
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
 use std::thread;
 use std::time::{Duration, Instant};
-use notify::{Watcher, RecursiveMode, Event};
+use tracing::{error, info, warn};
+use crate::solid::SolidFrame;
+
+/// How long each on/off phase lasts when flashing for an urgent workspace
+const URGENT_FLASH_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Main event loop for a child process
 pub fn run_event_loop(
@@ -26,11 +42,14 @@ pub fn run_event_loop(
     let target_display = args.display.as_ref().unwrap(); 
     
     // Load config using the path from args if provided, otherwise use default search
-    let snug_config = if let Some(path) = &args.config {
+    let mut snug_config = if let Some(path) = &args.config {
         load_config_silent(path)?
     } else {
         load_config_or_default()
     };
+    if let Err(e) = snug_config.set_profile(requested_profile(&args).as_deref()) {
+        warn!("{}, using the base config", e);
+    }
     
     // Get config for this display and merge with CLI args    
     let display_config = snug_config.get_display_config(target_display);
@@ -51,14 +70,20 @@ pub fn run_event_loop(
         compositor_state: CompositorState::bind(&globals, &qh)?,
         layer_shell: LayerShell::bind(&globals, &qh)?,
         shm: Shm::bind(&globals, &qh)?,
-        pool: None,
+        swapchain: None,
         layer: None,
         width: 0,
         height: 0,
         config: merged_config.clone(),
+        overlay: RuntimeOverlay::default(),
+        animation: None,
         bound_output: None,
         target_display_name: target_display.clone(),
         needs_recreation: false,
+        power: OutputPower::default(),
+        solid: SolidFrame::default(),
+        drawn: None,
+        draw_pending: false,
     };
     
     // Dispatch events to populate output_state
@@ -68,15 +93,16 @@ pub fn run_event_loop(
     let target_output = wayland::find_target_output(&mut temp_app, target_display);
     
     if target_output.is_none() {
-        eprintln!("Warning: Could not find output '{}', exiting", target_display);
+        warn!("Could not find output '{}', exiting", target_display);
         release_lock(target_display);
         return Ok(());
     }
     
     // Set up the layer surface
-    let (pool, layer) = wayland::setup_layer_surface(&mut temp_app, target_output.clone(), &qh)?;
+    let (swapchain, layer) = wayland::setup_layer_surface(&mut temp_app, target_output.clone(), &qh)?;
     
     // Now create the real App with the layer surface
+    let solid = SolidFrame::bind(&globals, &qh, &temp_app.compositor_state);
     let mut app = App {
         registry_state: temp_app.registry_state,
         output_state: temp_app.output_state,
@@ -84,15 +110,22 @@ pub fn run_event_loop(
         compositor_state: temp_app.compositor_state,
         layer_shell: temp_app.layer_shell,
         shm: temp_app.shm,
-        pool: Some(pool),
+        swapchain: Some(swapchain),
         layer: Some(layer),
         width: 0,
         height: 0,
         config: merged_config,
+        overlay: RuntimeOverlay::default(),
+        animation: None,
         bound_output: target_output.clone(),
         target_display_name: target_display.clone(),
         needs_recreation: false,
+        power: OutputPower::bind(&globals, &qh, args.track_power),
+        solid,
+        drawn: None,
+        draw_pending: false,
     };
+    app.power.watch(app.bound_output.as_ref(), &qh);
     
     conn.flush()?;
     
@@ -110,62 +143,41 @@ pub fn run_event_loop(
     
     // Set up config hot reload
     let config_needs_reload = Arc::new(Mutex::new(false));
-    setup_config_watcher(
+    watcher::setup_config_watcher(
         config_needs_reload.clone(),
         running.clone(),
         args.config.clone(), // FIXED: Pass the custom config path
     );
     
+    // Start compositor integrations (Hyprland, ...)
+    let (event_tx, event_rx) = mpsc::channel();
+    integrations::spawn(&snug_config, target_display, event_tx);
+    
+    // Accept `snug msg` commands on the control socket
+    let (msg_tx, msg_rx) = mpsc::channel();
+    if let Err(e) = ipc::spawn_listener(target_display, msg_tx) {
+        warn!("Failed to open control socket: {}", e);
+    }
+    
     // Run the main loop
-    main_loop(
-        app,
-        event_queue,
-        conn,
-        args,
+    let inputs = LoopInputs {
         running,
         config_needs_reload,
-    )
+        compositor_events: event_rx,
+        messages: msg_rx,
+    };
+    main_loop(app, event_queue, conn, args, snug_config, inputs)
 }
 
-/// Set up file watcher for config hot reload
-fn setup_config_watcher(
-    config_needs_reload: Arc<Mutex<bool>>,
+/// Flags and channels the main loop polls, filled in by other threads
+struct LoopInputs {
+    /// Cleared when snug should exit
     running: Arc<AtomicBool>,
-    custom_config_path: Option<String>,
-) {
-    thread::spawn(move || {
-        let config_path = custom_config_path
-            .map(std::path::PathBuf::from)
-            .unwrap_or_else(|| crate::config::get_config_path());
-        
-        eprintln!("Watching config file: {}", config_path.display());
-        
-        let (tx, rx) = std::sync::mpsc::channel();
-        let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
-            if let Ok(event) = res {
-                if event.kind.is_modify() {
-                    let _ = tx.send(());
-                }
-            }
-        }).expect("Failed to create file watcher");
-        
-        if watcher.watch(&config_path, RecursiveMode::NonRecursive).is_err() {
-            // Config file might not exist yet, silently continue
-            eprintln!("Config file does not exist yet: {}", config_path.display());
-            return;
-        }
-        
-        while running.load(Ordering::SeqCst) {
-            if rx.recv().is_ok() {
-                // Debounce multiple events
-                thread::sleep(Duration::from_millis(100));
-                while rx.try_recv().is_ok() {}
-                
-                eprintln!("Config file changed, reloading...");
-                *config_needs_reload.lock().unwrap() = true;
-            }
-        }
-    });
+    /// Set by the config watcher
+    config_needs_reload: Arc<Mutex<bool>>,
+    compositor_events: Receiver<CompositorEvent>,
+    /// `snug msg` commands from the control socket
+    messages: Receiver<MsgAction>,
 }
 
 /// Main event loop with config reload and surface lifecycle management
@@ -174,54 +186,167 @@ fn main_loop(
     mut event_queue: wayland_client::EventQueue<App>,
     conn: wayland_client::Connection,
     cli_args: Args,
-    running: Arc<AtomicBool>,
-    config_needs_reload: Arc<Mutex<bool>>,
+    mut snug_config: SnugConfig,
+    inputs: LoopInputs,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let LoopInputs { running, config_needs_reload, compositor_events, messages } = inputs;
     let display_name = app.target_display_name.clone();
     let qh = event_queue.handle();
+    let mut display_config = snug_config.get_display_config(&display_name);
+    let mut schedule = ScheduleDriver::new(requested_profile(&cli_args).is_some());
+    let mut indicator = IndicatorDriver::default();
+    let mut external: Option<ExternalSource> = None;
+
+    let mut compositor_status = CompositorStatus::default();
+    let mut last_flash = Instant::now();
 
     let mut last_dimensions = (app.width, app.height);
     let mut was_suspended = false;
     let mut resume_time: Option<Instant> = None;
     let mut last_draw_time = Instant::now();
 
+    // Ready is only reported once a frame is committed, which the configure
+    // may not have done, e.g. with the output off
+    let mut ready = false;
+    let mut status = String::new();
+    let mut watchdog = Watchdog::from_env();
+
     loop {
         if !running.load(Ordering::SeqCst) {
-            eprintln!("Compositor connection lost, exiting...");
+            info!("Compositor connection lost, exiting...");
+            notify::notify("STOPPING=1");
             break Ok(());
         }
+        watchdog.tick();
 
         // Config hot reload
         if *config_needs_reload.lock().unwrap() {
-            // FIXED: Load from custom config path if provided
-            let new_config = if let Some(path) = &cli_args.config {
-                match load_config_silent(path) {
-                    Ok(cfg) => cfg,
-                    Err(e) => {
-                        eprintln!("Failed to reload custom config from {}: {}", path, e);
-                        *config_needs_reload.lock().unwrap() = false;
-                        continue;
-                    }
+            // A broken edit keeps the frame as it was until the next good save
+            let mut new_config = match reload_config(cli_args.config.as_deref()) {
+                Ok(cfg) => cfg,
+                Err(e) => {
+                    error!("Failed to reload config, keeping the current one: {}", e);
+                    *config_needs_reload.lock().unwrap() = false;
+                    continue;
                 }
-            } else {
-                load_config_or_default()
             };
+            // Keep the profile switched to at runtime, unless it was removed
+            if let Err(e) = new_config.set_profile(snug_config.active_profile.as_deref()) {
+                warn!("{}, using the base config", e);
+            }
+            snug_config = new_config;
             
-            app.config = cli_args.merge_with_config(&new_config.get_display_config(&display_name));
-            if app.width > 0 && app.height > 0 {
-                app.draw();
+            display_config = snug_config.get_display_config(&display_name);
+            if apply_display_config(&mut app, &qh, &cli_args, &display_config, &compositor_status) {
                 conn.flush()?;
                 last_draw_time = Instant::now();
-                eprintln!("Config reloaded and redrawn");
+                info!("Config reloaded and redrawn");
             }
+            schedule.wake();
+            indicator.wake();
             *config_needs_reload.lock().unwrap() = false;
         }
 
+        // Time-of-day profile switches and fades
+        if let Some((scheduled, frame)) = schedule.tick(&mut snug_config, &display_name, &cli_args) {
+            display_config = scheduled;
+            if app.config != frame {
+                app.config = frame;
+                indicator.wake();
+                app.overlay.set("compositor", compositor_status.frame_override(&display_config));
+                if app.width > 0 && app.height > 0 {
+                    app.draw();
+                    conn.flush()?;
+                    last_draw_time = Instant::now();
+                }
+            }
+        }
+
+        // Battery / temperature colour, only redrawn when the colour changes
+        if let Some(layer) = indicator.tick(&display_config, &snug_config.indicator)
+            && app.overlay.set("indicator", layer)
+            && app.width > 0 && app.height > 0
+        {
+            app.draw();
+            conn.flush()?;
+            last_draw_time = Instant::now();
+        }
+
+        // `source` command or FIFO, restarted whenever the option changes
+        let source_changed = ExternalSource::sync(&mut external, display_config.source.as_deref())
+            && app.overlay.clear("source");
+        let source_updated = external
+            .as_ref()
+            .and_then(ExternalSource::latest)
+            .is_some_and(|layer| app.overlay.set("source", layer));
+        if (source_changed || source_updated) && app.width > 0 && app.height > 0 {
+            app.draw();
+            conn.flush()?;
+            last_draw_time = Instant::now();
+        }
+
+        // Apply workspace/fullscreen changes reported by compositor integrations
+        let mut status_changed = false;
+        while let Ok(event) = compositor_events.try_recv() {
+            compositor_status.update(event);
+            status_changed = true;
+        }
+        // Blink the frame while a workspace is urgent
+        if compositor_status.urgent && last_flash.elapsed() >= URGENT_FLASH_INTERVAL {
+            compositor_status.flash_on = !compositor_status.flash_on;
+            last_flash = Instant::now();
+            status_changed = true;
+        }
+        if status_changed
+            && app.overlay.set("compositor", compositor_status.frame_override(&display_config))
+            && app.width > 0 && app.height > 0
+        {
+            app.draw();
+            conn.flush()?;
+            last_draw_time = Instant::now();
+        }
+
+        // Flash/pulse and profile requests from `snug msg`
+        while let Ok(action) = messages.try_recv() {
+            if let MsgAction::Profile { name } = &action {
+                match snug_config.set_profile(name.as_deref()) {
+                    Ok(()) => {
+                        info!("Switched to profile: {}", name.as_deref().unwrap_or("(base)"));
+                        schedule.hold();
+                        display_config = snug_config.get_display_config(&display_name);
+                        indicator.wake();
+                        if apply_display_config(&mut app, &qh, &cli_args, &display_config, &compositor_status) {
+                            conn.flush()?;
+                            last_draw_time = Instant::now();
+                        }
+                    }
+                    Err(e) => warn!("{}", e),
+                }
+                continue;
+            }
+            if let Some(animation) = Animation::from_action(&action) {
+                app.start_animation(&qh, animation);
+                conn.flush()?;
+                last_draw_time = Instant::now();
+            }
+        }
+
+        // Profiles change by message, schedule and reload
+        let current = status_line(&snug_config);
+        if !ready && app.drawn.is_some() {
+            notify::notify(&format!("READY=1\nSTATUS={}", current));
+            ready = true;
+            status = current;
+        } else if ready && current != status {
+            notify::notify(&format!("STATUS={}", current));
+            status = current;
+        }
+
         // Check if bound output disappeared (zombie layer)
         if let Some(bound) = &app.bound_output {
             let output_exists = app.output_state.outputs().any(|o| o.id() == bound.id());
             if !output_exists {
-                eprintln!("[{}] Zombie surface detected, clearing and searching by name...", display_name);
+                warn!("Zombie surface detected, clearing and searching by name...");
                 app.layer = None;
                 app.bound_output = None;
                 app.width = 0;
@@ -262,23 +387,44 @@ fn main_loop(
             std::process::exit(0);
         }
 
+        // Redraw exactly once when the output powers back on
+        if app.power.take_restored() && app.width > 0 && app.height > 0 {
+            app.invalidate();
+            if app.animation.is_some() {
+                app.animate(&qh);
+            } else {
+                app.draw();
+            }
+            conn.flush()?;
+            last_draw_time = Instant::now();
+        }
+
+        // A draw that found both buffers busy, now that one may be released
+        if app.draw_pending && app.width > 0 && app.height > 0 {
+            app.draw();
+            conn.flush()?;
+            last_draw_time = Instant::now();
+        }
+
         // Detect dimension changes (suspend/resume)
         let current_dimensions = (app.width, app.height);
         if current_dimensions != last_dimensions {
             if current_dimensions.0 == 0 || current_dimensions.1 == 0 {
                 was_suspended = true;
                 resume_time = None;
-            } else if was_suspended {
+            } else if was_suspended && !app.power.is_tracked() {
+                // Without power management, a configure after a zero size
+                // is the only sign of a resume
                 force_layer_recommit(&app);
-                for _ in 0..3 {
-                    thread::sleep(Duration::from_millis(100));
-                    app.draw();
-                    conn.flush()?;
-                }
+                // The compositor may have dropped the old buffer, so draw it again
+                app.invalidate();
+                app.draw();
+                conn.flush()?;
                 was_suspended = false;
                 resume_time = Some(Instant::now());
                 last_draw_time = Instant::now();
             } else {
+                was_suspended = false;
                 app.draw();
                 conn.flush()?;
                 last_draw_time = Instant::now();
@@ -288,7 +434,7 @@ fn main_loop(
 
         // Recreate layer if lost during DPMS
         if app.layer.is_none() && app.width > 0 && app.height > 0 && app.bound_output.is_some() {
-            eprintln!("[{}] Layer surface lost, recreating...", display_name);
+            warn!("Layer surface lost, recreating...");
             app.recreate_layer_surface(&qh, app.bound_output.clone());
             wait_for_configure(&mut event_queue, &mut app, 20)?;
             app.draw();
@@ -296,9 +442,11 @@ fn main_loop(
             last_draw_time = Instant::now();
         }
 
-        // High-refresh post-resume redraws
+        // High-refresh post-resume redraws, unless power changes are reported
         if let Some(resume) = resume_time {
-            if resume.elapsed() < Duration::from_secs(10) && last_draw_time.elapsed() > Duration::from_secs(2) {
+            if app.power.is_tracked() {
+                resume_time = None;
+            } else if resume.elapsed() < Duration::from_secs(10) && last_draw_time.elapsed() > Duration::from_secs(2) {
                 if app.width > 0 && app.height > 0 {
                     app.draw();
                     conn.flush()?;
@@ -309,9 +457,71 @@ fn main_loop(
             }
         }
 
-        let _ = event_queue.dispatch_pending(&mut app);
-        thread::sleep(Duration::from_millis(50));
+        dispatch_with_timeout(&mut event_queue, &mut app, Duration::from_millis(50))?;
+    }
+}
+
+/// What `systemctl status` shows for this instance
+fn status_line(snug_config: &SnugConfig) -> String {
+    match &snug_config.active_profile {
+        Some(profile) => format!("drawing, profile {}", profile),
+        None => "drawing".to_string(),
+    }
+}
+
+/// Switch the frame to a new display config, with the config's transition if it has one
+///
+/// Returns whether anything was drawn and needs flushing.
+fn apply_display_config(
+    app: &mut App,
+    qh: &QueueHandle<App>,
+    cli_args: &Args,
+    display_config: &DisplayConfig,
+    compositor_status: &CompositorStatus,
+) -> bool {
+    let previous = app.effective_config();
+    app.config = cli_args.merge_with_config(display_config);
+    app.overlay.set("compositor", compositor_status.frame_override(display_config));
+    if app.width == 0 || app.height == 0 {
+        return false;
     }
+    match display_config.transition() {
+        Some((duration, easing)) => app.start_animation(qh, Animation::transition(previous, duration, easing)),
+        None => app.draw(),
+    }
+    true
+}
+
+/// Wait up to `timeout` for Wayland events and dispatch them
+///
+/// Unlike sleeping and calling `dispatch_pending`, this actually reads the
+/// socket and wakes as soon as something arrives, so frame callbacks driving
+/// animations are handled at the output's refresh rate.
+fn dispatch_with_timeout(
+    event_queue: &mut wayland_client::EventQueue<App>,
+    app: &mut App,
+    timeout: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    event_queue.flush()?;
+    event_queue.dispatch_pending(app)?;
+    
+    if let Some(guard) = event_queue.prepare_read() {
+        let ready = {
+            let mut fds = [PollFd::new(guard.connection_fd(), PollFlags::POLLIN)];
+            let timeout = PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX);
+            match poll(&mut fds, timeout) {
+                Ok(n) => n > 0,
+                Err(nix::errno::Errno::EINTR) => false,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        if ready {
+            guard.read()?;
+        }
+    }
+    
+    event_queue.dispatch_pending(app)?;
+    Ok(())
 }
 
 /// Helper to wait for configure events
@@ -0,0 +1,369 @@
+use crate::colour::Colour;
+use crate::logging;
+use crate::notify::NOTIFY_ENV;
+use crate::overlay::FrameOverride;
+use crate::paths::expand_tilde;
+use serde::Deserialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::fd::AsFd;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::prctl::set_pdeathsig;
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use tracing::warn;
+
+/// Pause before restarting a source command that exited
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// How often, in milliseconds, an idle FIFO reader checks whether it was stopped
+const FIFO_POLL_MS: u16 = 250;
+
+/// One line from a source, e.g. `{"color":"ff0000","opacity":0.8,"visible":true}`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Update {
+    color: Option<String>,
+    opacity: Option<f64>,
+    radius: Option<i32>,
+    visible: Option<bool>,
+}
+
+/// Parse a JSON line into the layer it asks for
+///
+/// Every line describes the whole layer, so `{}` hands the frame back to
+/// the config.
+pub fn parse_line(line: &str) -> Result<FrameOverride, String> {
+    let update: Update = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    if let Some(color) = &update.color {
+        Colour::parse(color).map_err(|e| format!("color: {}", e))?;
+    }
+    if let Some(opacity) = update.opacity
+        && !(0.0..=1.0).contains(&opacity)
+    {
+        return Err(format!("opacity: must be between 0 and 1, got {}", opacity));
+    }
+    if update.radius.is_some_and(|radius| radius < 0) {
+        return Err("radius: must not be negative".to_string());
+    }
+    Ok(FrameOverride {
+        color: update.color,
+        opacity: update.opacity,
+        radius: update.radius,
+        borders: None,
+        hidden: update.visible == Some(false),
+    })
+}
+
+/// The display's `source` option, running in the background
+///
+/// A path to a named pipe is read, reopening it whenever the writer closes;
+/// anything else runs through `sh -c` and is restarted if it exits. Dropping
+/// the handle stops the command.
+pub struct ExternalSource {
+    spec: String,
+    updates: Receiver<FrameOverride>,
+    stop: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl ExternalSource {
+    pub fn start(spec: &str) -> Self {
+        let (tx, updates) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let child = Arc::new(Mutex::new(None));
+
+        let worker = Worker {
+            spec: spec.to_string(),
+            tx,
+            stop: stop.clone(),
+            child: child.clone(),
+        };
+        logging::spawn(move || worker.run());
+
+        Self {
+            spec: spec.to_string(),
+            updates,
+            stop,
+            child,
+        }
+    }
+
+    /// Latest layer sent since the last call, older ones are superseded
+    pub fn latest(&self) -> Option<FrameOverride> {
+        self.updates.try_iter().last()
+    }
+
+    /// Start, restart or stop `current` to match the display's `source`
+    ///
+    /// Returns whether it changed, in which case the old layer is stale.
+    pub fn sync(current: &mut Option<Self>, wanted: Option<&str>) -> bool {
+        if current.as_ref().map(|source| source.spec.as_str()) == wanted {
+            return false;
+        }
+        *current = wanted.map(Self::start);
+        true
+    }
+}
+
+impl Drop for ExternalSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(child) = self.child.lock().unwrap().take() {
+            terminate(child);
+        }
+    }
+}
+
+/// Stop the shell and whatever it started, which share its process group
+fn terminate(mut child: Child) {
+    let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGTERM);
+    let _ = child.wait();
+}
+
+struct Worker {
+    spec: String,
+    tx: Sender<FrameOverride>,
+    stop: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl Worker {
+    fn run(self) {
+        let path = expand_tilde(Path::new(&self.spec));
+        let is_fifo = fs::metadata(&path).is_ok_and(|meta| meta.file_type().is_fifo());
+
+        while !self.stopped() {
+            if is_fifo {
+                // Never blocks, so a dropped handle can't leave the thread waiting for a writer
+                let fifo = OpenOptions::new().read(true).custom_flags(OFlag::O_NONBLOCK.bits()).open(&path);
+                match fifo {
+                    Ok(file) => self.forward(StoppableFifo { file, stop: &self.stop }),
+                    Err(e) => {
+                        warn!("Source {}: {}", path.display(), e);
+                        return;
+                    }
+                }
+                continue;
+            }
+
+            match self.spawn() {
+                Ok(stdout) => {
+                    self.forward(stdout);
+                    let status = self.child.lock().unwrap().take().map(|mut child| child.wait());
+                    if self.stopped() {
+                        return;
+                    }
+                    match status {
+                        Some(Ok(status)) => warn!("Source command exited ({}), restarting", status),
+                        _ => warn!("Source command exited, restarting"),
+                    }
+                }
+                Err(e) => warn!("Could not run source '{}': {}", self.spec, e),
+            }
+            // A crashed status script shouldn't leave its last colour behind
+            if self.tx.send(FrameOverride::default()).is_err() {
+                return;
+            }
+            thread::sleep(RESTART_DELAY);
+        }
+    }
+
+    /// Start the command, keeping the child so dropping the handle can kill it
+    fn spawn(&self) -> io::Result<ChildStdout> {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&self.spec)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .env_remove(NOTIFY_ENV)
+            .process_group(0);
+        // SAFETY: only calls prctl between fork and exec
+        unsafe {
+            command.pre_exec(|| {
+                // Don't outlive snug
+                set_pdeathsig(Some(Signal::SIGTERM))?;
+                Ok(())
+            });
+        }
+
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut slot = self.child.lock().unwrap();
+        if self.stopped() {
+            terminate(child);
+        } else {
+            *slot = Some(child);
+        }
+        Ok(stdout)
+    }
+
+    /// Send each valid line on until EOF or the handle is dropped
+    fn forward(&self, reader: impl Read) {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else { break };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match parse_line(line) {
+                Ok(layer) => {
+                    if self.tx.send(layer).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => warn!("Ignoring source line '{}': {}", line, e),
+            }
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+}
+
+/// A FIFO opened with `O_NONBLOCK`, read as if blocking until the source stops
+///
+/// EOF comes once a writer closes the pipe, or as soon as the handle is dropped.
+/// Until a writer first opens the pipe, there is simply nothing to read.
+struct StoppableFifo<'a> {
+    file: File,
+    stop: &'a AtomicBool,
+}
+
+impl Read for StoppableFifo<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.stop.load(Ordering::SeqCst) {
+                return Ok(0);
+            }
+            let mut fds = [PollFd::new(self.file.as_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, PollTimeout::from(FIFO_POLL_MS)) {
+                // Readable, or POLLHUP after the writer left, which reads as EOF
+                Ok(ready) if ready > 0 => {}
+                Ok(_) | Err(Errno::EINTR) => continue,
+                Err(e) => return Err(e.into()),
+            }
+            match self.file.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use nix::sys::signal::kill;
+    use nix::sys::stat::Mode;
+    use nix::unistd::mkfifo;
+    use std::io::Write;
+    use std::time::Instant;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn lines_parse_into_layers() {
+        assert_eq!(
+            parse_line(r#"{"color":"ff0000","opacity":0.8,"radius":4,"visible":true}"#),
+            Ok(FrameOverride {
+                color: Some("ff0000".to_string()),
+                opacity: Some(0.8),
+                radius: Some(4),
+                borders: None,
+                hidden: false,
+            })
+        );
+        assert_eq!(parse_line(r#"{"visible":false}"#).map(|layer| layer.hidden), Ok(true));
+        assert_eq!(parse_line("{}"), Ok(FrameOverride::default()));
+    }
+
+    #[test]
+    fn bad_lines_are_rejected() {
+        for line in [
+            "not json",
+            r#"{"colour":"ff0000"}"#,
+            r#"{"color":"nope"}"#,
+            r#"{"opacity":1.5}"#,
+            r#"{"radius":-1}"#,
+        ] {
+            assert!(parse_line(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn command_output_is_forwarded_and_stopped_on_drop() {
+        let dir = TempDir::new("source");
+        let pid_file = dir.path().join("pid");
+        let script = format!(
+            r#"printf '%s\n' '{{"color":"ff0000"}}' 'not json' '{{"visible":false}}'; echo $$ > {}; exec sleep 30"#,
+            pid_file.display()
+        );
+        let source = ExternalSource::start(&script);
+
+        let first = source.updates.recv_timeout(WAIT).unwrap();
+        assert_eq!(first.color.as_deref(), Some("ff0000"));
+        assert!(source.updates.recv_timeout(WAIT).unwrap().hidden);
+
+        let deadline = Instant::now() + WAIT;
+        let pid = loop {
+            if let Some(pid) = fs::read_to_string(&pid_file).ok().and_then(|pid| pid.trim().parse().ok()) {
+                break Pid::from_raw(pid);
+            }
+            assert!(Instant::now() < deadline, "script never wrote its pid");
+            thread::sleep(Duration::from_millis(10));
+        };
+        drop(source);
+        assert_eq!(kill(pid, None), Err(Errno::ESRCH));
+    }
+
+    /// Open the write end without blocking, waiting up to `timeout` for a reader
+    fn open_writer(path: &Path, timeout: Duration) -> Option<File> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match OpenOptions::new().write(true).custom_flags(OFlag::O_NONBLOCK.bits()).open(path) {
+                Ok(file) => return Some(file),
+                Err(e) if e.raw_os_error() == Some(Errno::ENXIO as i32) && Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) if e.raw_os_error() == Some(Errno::ENXIO as i32) => return None,
+                Err(e) => panic!("{}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn fifo_is_reopened_and_released_on_drop() {
+        let dir = TempDir::new("fifo");
+        let path = dir.path().join("frame");
+        mkfifo(&path, Mode::S_IRUSR | Mode::S_IWUSR).unwrap();
+        let source = ExternalSource::start(path.to_str().unwrap());
+
+        for color in ["ff0000", "00ff00"] {
+            let mut writer = open_writer(&path, WAIT).expect("source never opened the FIFO");
+            writeln!(writer, r#"{{"color":"{}"}}"#, color).unwrap();
+            let layer = source.updates.recv_timeout(WAIT).unwrap();
+            assert_eq!(layer.color.as_deref(), Some(color));
+        }
+
+        // The worker must let go of the pipe even though no writer is coming
+        thread::sleep(Duration::from_millis(100));
+        drop(source);
+        thread::sleep(Duration::from_millis(u64::from(FIFO_POLL_MS) * 3));
+        assert!(open_writer(&path, Duration::ZERO).is_none(), "reader still open after drop");
+    }
+}
@@ -2,6 +2,7 @@ use crate::app::App;
 use smithay_client_toolkit::{
     compositor::CompositorHandler,
     delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_seat, delegate_shm,
+    delegate_subcompositor,
     output::OutputHandler,
     registry::ProvidesRegistryState,
     seat::{Capability, SeatHandler},
@@ -20,7 +21,7 @@ use wayland_client::{
 impl CompositorHandler for App {
     fn scale_factor_changed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: i32) {}
     fn transform_changed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: wl_output::Transform) {}
-    fn frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) { self.draw(); }
+    fn frame(&mut self, _: &Connection, qh: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) { self.animate(qh); }
     fn surface_enter(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
     fn surface_leave(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
 }
@@ -86,6 +87,8 @@ impl LayerShellHandler for App {
             layer.commit();
         }
 
+        // A configure must be answered with a commit, even if the frame is the same
+        self.invalidate();
         self.draw();
     }
 
@@ -120,5 +123,6 @@ delegate_compositor!(App);
 delegate_output!(App);
 delegate_seat!(App);
 delegate_shm!(App);
+delegate_subcompositor!(App);
 delegate_layer!(App);
 delegate_registry!(App);
@@ -0,0 +1,354 @@
+use crate::args::parse_duration;
+use crate::config::{DisplayConfig, IndicatorConfig};
+use crate::overlay::FrameOverride;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Machine state an indicator display shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// Charge in percent, from `class/power_supply`
+    Battery,
+    /// Temperature in °C, from `class/thermal`
+    Thermal,
+}
+
+impl Source {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "battery" => Some(Self::Battery),
+            "thermal" => Some(Self::Thermal),
+            _ => None,
+        }
+    }
+}
+
+/// One sample of machine state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub value: f64,
+    /// On mains power, battery only
+    pub charging: bool,
+}
+
+/// Sample `source` under `root`, normally `/sys`
+///
+/// `device` picks a power supply (`BAT1`) or thermal zone (`thermal_zone2`);
+/// without one the first battery, or the hottest zone, is used.
+pub fn read(root: &Path, source: Source, device: Option<&str>) -> io::Result<Reading> {
+    match source {
+        Source::Battery => read_battery(&root.join("class/power_supply"), device),
+        Source::Thermal => read_thermal(&root.join("class/thermal"), device),
+    }
+}
+
+fn read_battery(dir: &Path, device: Option<&str>) -> io::Result<Reading> {
+    let battery = match device {
+        Some(name) => dir.join(name),
+        None => entries(dir)?
+            .into_iter()
+            .find(|entry| read_trimmed(&entry.join("type")).is_ok_and(|kind| kind == "Battery"))
+            .ok_or_else(|| not_found(format!("no battery under {}", dir.display())))?,
+    };
+    let capacity = read_number(&battery.join("capacity"))?;
+    let status = read_trimmed(&battery.join("status")).unwrap_or_default();
+    Ok(Reading {
+        value: capacity,
+        charging: matches!(status.as_str(), "Charging" | "Full"),
+    })
+}
+
+fn read_thermal(dir: &Path, device: Option<&str>) -> io::Result<Reading> {
+    let millidegrees = match device {
+        Some(zone) => read_number(&dir.join(zone).join("temp"))?,
+        None => entries(dir)?
+            .iter()
+            .filter(|entry| entry.file_name().is_some_and(|name| name.to_string_lossy().starts_with("thermal_zone")))
+            .filter_map(|zone| read_number(&zone.join("temp")).ok())
+            .reduce(f64::max)
+            .ok_or_else(|| not_found(format!("no thermal zone with a temperature under {}", dir.display())))?,
+    };
+    Ok(Reading {
+        value: millidegrees / 1000.0,
+        charging: false,
+    })
+}
+
+/// Directory entries sorted by name, so the pick is stable across boots
+fn entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| with_path(dir, e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+fn read_trimmed(path: &Path) -> io::Result<String> {
+    Ok(fs::read_to_string(path).map_err(|e| with_path(path, e))?.trim().to_string())
+}
+
+fn with_path(path: &Path, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+fn read_number(path: &Path) -> io::Result<f64> {
+    let text = read_trimmed(path)?;
+    text.parse().map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}: expected a number, got '{}'", path.display(), text))
+    })
+}
+
+fn not_found(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
+
+/// Parse `indicator.interval`, which needs a unit
+///
+/// A bare number would be milliseconds like other durations, and `5` meaning
+/// 5ms would hammer sysfs.
+pub fn parse_interval(value: &str) -> Result<Duration, String> {
+    if value.trim().parse::<f64>().is_ok() {
+        return Err(format!("'{}' needs a unit, e.g. \"{}s\"", value, value.trim()));
+    }
+    match parse_duration(value)? {
+        interval if interval.is_zero() => Err("must be longer than 0".to_string()),
+        interval => Ok(interval),
+    }
+}
+
+/// Parse `["20:ff5555", "50:ffb86c"]` into thresholds sorted low to high
+pub fn thresholds(entries: &[String]) -> Result<Vec<(f64, &str)>, String> {
+    let mut thresholds = entries
+        .iter()
+        .map(|entry| {
+            let (threshold, colour) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("expected \"threshold:colour\", got '{}'", entry))?;
+            let threshold: f64 = threshold
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}': threshold must be a number", entry))?;
+            Ok((threshold, colour.trim()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    thresholds.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(thresholds)
+}
+
+/// The charging colour, else the first threshold at or above the value, else the highest
+pub fn colour_for(display_config: &DisplayConfig, reading: Reading) -> Option<String> {
+    if reading.charging && let Some(colour) = &display_config.indicator_charging_color {
+        return Some(colour.clone());
+    }
+    let thresholds = thresholds(display_config.indicator_colors.as_deref()?).ok()?;
+    thresholds
+        .iter()
+        .find(|(threshold, _)| reading.value <= *threshold)
+        .or(thresholds.last())
+        .map(|(_, colour)| colour.to_string())
+}
+
+/// Polls sysfs for a running instance
+#[derive(Default)]
+pub struct IndicatorDriver {
+    next_check: Option<Instant>,
+    /// Whether the last read failed, so a missing battery is reported once
+    failing: bool,
+}
+
+impl IndicatorDriver {
+    /// Read on the next tick, e.g. after the display config changed
+    pub fn wake(&mut self) {
+        self.next_check = None;
+    }
+
+    /// The indicator layer for the frame, when a read is due
+    ///
+    /// Returns an empty layer when the display has no indicator or sysfs
+    /// can't be read, which clears any colour set earlier.
+    pub fn tick(&mut self, display_config: &DisplayConfig, settings: &IndicatorConfig) -> Option<FrameOverride> {
+        let now = Instant::now();
+        if self.next_check.is_some_and(|next| now < next) {
+            return None;
+        }
+        self.next_check = Some(now + settings.interval);
+
+        let Some(source) = display_config.indicator.as_deref().and_then(Source::parse) else {
+            return Some(FrameOverride::default());
+        };
+        match read(&settings.sysfs_root, source, display_config.indicator_device.as_deref()) {
+            Ok(reading) => {
+                self.failing = false;
+                Some(FrameOverride {
+                    color: colour_for(display_config, reading),
+                    ..FrameOverride::default()
+                })
+            }
+            Err(e) => {
+                if !self.failing {
+                    warn!("Indicator: {}", e);
+                }
+                self.failing = true;
+                Some(FrameOverride::default())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// A sysfs tree with mains power and one battery
+    fn power_supplies(capacity: &str, status: &str) -> TempDir {
+        let root = TempDir::new("sysfs");
+        root.write("class/power_supply/AC/type", "Mains\n");
+        root.write("class/power_supply/AC/online", "0\n");
+        root.write("class/power_supply/BAT0/type", "Battery\n");
+        root.write("class/power_supply/BAT0/capacity", capacity);
+        root.write("class/power_supply/BAT0/status", status);
+        root
+    }
+
+    fn display(thresholds: &[&str], charging: Option<&str>) -> DisplayConfig {
+        DisplayConfig {
+            indicator: Some("battery".into()),
+            indicator_colors: Some(thresholds.iter().map(|entry| entry.to_string()).collect()),
+            indicator_charging_color: charging.map(str::to_string),
+            ..DisplayConfig::default()
+        }
+    }
+
+    #[test]
+    fn intervals_need_a_unit() {
+        assert_eq!(parse_interval("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_interval("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_interval("5"), Err("'5' needs a unit, e.g. \"5s\"".to_string()));
+        assert_eq!(parse_interval("0s"), Err("must be longer than 0".to_string()));
+        assert!(parse_interval("soon").is_err());
+    }
+
+    #[test]
+    fn battery_is_found_by_type() {
+        let root = power_supplies("42\n", "Discharging\n");
+        let reading = read(root.path(), Source::Battery, None).unwrap();
+        assert_eq!(reading, Reading { value: 42.0, charging: false });
+
+        root.write("class/power_supply/BAT0/status", "Full\n");
+        assert!(read(root.path(), Source::Battery, None).unwrap().charging);
+        // A missing status reads as discharging
+        fs::remove_file(root.path().join("class/power_supply/BAT0/status")).unwrap();
+        assert!(!read(root.path(), Source::Battery, None).unwrap().charging);
+    }
+
+    #[test]
+    fn battery_device_is_used_as_named() {
+        let root = power_supplies("42\n", "Discharging\n");
+        root.write("class/power_supply/BAT1/capacity", "97\n");
+        root.write("class/power_supply/BAT1/status", "Charging\n");
+        let reading = read(root.path(), Source::Battery, Some("BAT1")).unwrap();
+        assert_eq!(reading, Reading { value: 97.0, charging: true });
+
+        let error = read(root.path(), Source::Battery, Some("BAT7")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("BAT7/capacity"), "{}", error);
+    }
+
+    #[test]
+    fn battery_errors() {
+        let root = power_supplies("lots\n", "Discharging\n");
+        let error = read(root.path(), Source::Battery, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().ends_with("expected a number, got 'lots'"), "{}", error);
+
+        fs::remove_dir_all(root.path().join("class/power_supply/BAT0")).unwrap();
+        let error = read(root.path(), Source::Battery, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(error.to_string().starts_with("no battery under"), "{}", error);
+
+        let empty = TempDir::new("sysfs");
+        assert_eq!(read(empty.path(), Source::Battery, None).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn thermal_uses_the_hottest_readable_zone() {
+        let root = TempDir::new("sysfs");
+        root.write("class/thermal/thermal_zone0/temp", "45000\n");
+        root.write("class/thermal/thermal_zone1/temp", "61500\n");
+        root.write("class/thermal/thermal_zone2/temp", "garbage\n");
+        root.write("class/thermal/cooling_device0/temp", "99000\n");
+
+        assert_eq!(read(root.path(), Source::Thermal, None).unwrap().value, 61.5);
+        assert_eq!(read(root.path(), Source::Thermal, Some("thermal_zone0")).unwrap().value, 45.0);
+        let error = read(root.path(), Source::Thermal, Some("thermal_zone2")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_dir_all(root.path().join("class/thermal/thermal_zone0")).unwrap();
+        fs::remove_dir_all(root.path().join("class/thermal/thermal_zone1")).unwrap();
+        let error = read(root.path(), Source::Thermal, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn thresholds_are_sorted_and_validated() {
+        let entries: Vec<String> = ["50:ffb86c", " 20 : ff5555 ", "100:wal:color2"].map(String::from).to_vec();
+        assert_eq!(
+            thresholds(&entries).unwrap(),
+            vec![(20.0, "ff5555"), (50.0, "ffb86c"), (100.0, "wal:color2")]
+        );
+
+        assert_eq!(
+            thresholds(&["ff5555".to_string()]).unwrap_err(),
+            "expected \"threshold:colour\", got 'ff5555'"
+        );
+        assert_eq!(
+            thresholds(&["low:ff5555".to_string()]).unwrap_err(),
+            "'low:ff5555': threshold must be a number"
+        );
+        assert!(thresholds(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn colour_for_picks_the_first_threshold_at_or_above() {
+        let config = display(&["50:yellow", "20:red", "100:green"], Some("blue"));
+        let colour = |value: f64, charging: bool| colour_for(&config, Reading { value, charging });
+        assert_eq!(colour(5.0, false).as_deref(), Some("red"));
+        assert_eq!(colour(20.0, false).as_deref(), Some("red"));
+        assert_eq!(colour(20.5, false).as_deref(), Some("yellow"));
+        assert_eq!(colour(100.0, false).as_deref(), Some("green"));
+        // Above every threshold, e.g. a hot CPU, keeps the highest colour
+        assert_eq!(colour(120.0, false).as_deref(), Some("green"));
+        assert_eq!(colour(5.0, true).as_deref(), Some("blue"));
+
+        let no_charging = display(&["20:red"], None);
+        assert_eq!(colour_for(&no_charging, Reading { value: 5.0, charging: true }).as_deref(), Some("red"));
+        let broken = display(&["red"], None);
+        assert_eq!(colour_for(&broken, Reading { value: 5.0, charging: false }), None);
+    }
+
+    #[test]
+    fn driver_clears_the_colour_when_sysfs_goes_bad() {
+        let root = power_supplies("15\n", "Discharging\n");
+        let settings = IndicatorConfig {
+            sysfs_root: root.path().to_path_buf(),
+            interval: Duration::from_secs(60),
+        };
+        let config = display(&["20:red", "100:green"], None);
+        let mut driver = IndicatorDriver::default();
+
+        assert_eq!(driver.tick(&config, &settings).unwrap().color.as_deref(), Some("red"));
+        // Not due again until the interval passed
+        assert_eq!(driver.tick(&config, &settings), None);
+
+        root.write("class/power_supply/BAT0/capacity", "\n");
+        driver.wake();
+        assert_eq!(driver.tick(&config, &settings), Some(FrameOverride::default()));
+        assert!(driver.failing);
+    }
+}
@@ -0,0 +1,405 @@
+//! Hyprland IPC integration
+//!
+//! Hyprland publishes events as `EVENT>>DATA` lines on `.socket2.sock` and
+//! answers `hyprctl`-style requests on `.socket.sock`, both living in
+//! `$XDG_RUNTIME_DIR/hypr/$HYPRLAND_INSTANCE_SIGNATURE/`.
+
+use super::CompositorEvent;
+use std::collections::HashMap;
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+/// Find the socket directory of the running Hyprland instance
+pub fn socket_dir() -> Option<PathBuf> {
+    let signature = env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+    let candidates = [
+        crate::paths::runtime_dir().join("hypr").join(&signature),
+        // Hyprland before 0.40 kept its sockets in /tmp
+        PathBuf::from("/tmp/hypr").join(&signature),
+    ];
+
+    candidates.into_iter().find(|dir| dir.join(".socket2.sock").exists())
+}
+
+/// Send a request on `.socket.sock` and return the raw reply
+pub fn request(dir: &Path, command: &str) -> std::io::Result<String> {
+    let mut stream = UnixStream::connect(dir.join(".socket.sock"))?;
+    stream.write_all(command.as_bytes())?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
+/// Listen for events until the socket closes, forwarding changes for `display`
+pub fn run(
+    dir: &Path,
+    display: &str,
+    sync_gaps: bool,
+    tx: Sender<CompositorEvent>,
+) -> std::io::Result<()> {
+    let stream = UnixStream::connect(dir.join(".socket2.sock"))?;
+    let mut tracker = Tracker::new(display);
+
+    // Seed the tracker so we don't have to wait for the first workspace switch
+    if let Ok(reply) = request(dir, "monitors") {
+        tracker.seed_monitors(&reply);
+    }
+    if let Ok(reply) = request(dir, "workspaces") {
+        tracker.seed_workspaces(&reply);
+    }
+    for event in tracker.changes() {
+        if tx.send(event).is_err() {
+            return Ok(());
+        }
+    }
+    if sync_gaps {
+        send_gaps(dir, &tx);
+    }
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if sync_gaps && line.starts_with("configreloaded>>") {
+            send_gaps(dir, &tx);
+        }
+        tracker.handle_line(&line);
+        for event in tracker.changes() {
+            if tx.send(event).is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn send_gaps(dir: &Path, tx: &Sender<CompositorEvent>) {
+    if let Some(gaps) = request(dir, "getoption general:gaps_out").ok().and_then(|r| parse_gaps(&r)) {
+        let _ = tx.send(CompositorEvent::Gaps(gaps));
+    }
+}
+
+/// Parse a `getoption general:gaps_out` reply into (top, right, bottom, left)
+///
+/// Recent Hyprland versions reply with `custom type: 10 20 10 20`, older ones
+/// with `int: 10`. Both follow the CSS shorthand rules.
+pub fn parse_gaps(reply: &str) -> Option<[i32; 4]> {
+    let values = reply.lines().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("custom type:")
+            .or_else(|| line.strip_prefix("int:"))
+            .map(str::trim)
+    })?;
+    let values: Vec<i32> = values
+        .split_whitespace()
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match values[..] {
+        [all] => Some([all, all, all, all]),
+        [vertical, horizontal] => Some([vertical, horizontal, vertical, horizontal]),
+        [top, horizontal, bottom] => Some([top, horizontal, bottom, horizontal]),
+        [top, right, bottom, left] => Some([top, right, bottom, left]),
+        _ => None,
+    }
+}
+
+/// Follows Hyprland's monitor and workspace state and reports changes for one output
+pub struct Tracker {
+    display: String,
+    focused_monitor: Option<String>,
+    active: HashMap<String, String>,
+    special: HashMap<String, String>,
+    fullscreen: HashMap<String, bool>,
+    last_workspace: Option<String>,
+    last_fullscreen: bool,
+}
+
+impl Tracker {
+    pub fn new(display: &str) -> Self {
+        Self {
+            display: display.to_string(),
+            focused_monitor: None,
+            active: HashMap::new(),
+            special: HashMap::new(),
+            fullscreen: HashMap::new(),
+            last_workspace: None,
+            last_fullscreen: false,
+        }
+    }
+
+    /// Seed monitor state from the plain-text `monitors` reply
+    pub fn seed_monitors(&mut self, reply: &str) {
+        let mut current: Option<String> = None;
+        for line in reply.lines() {
+            let trimmed = line.trim();
+            if let Some(rest) = trimmed.strip_prefix("Monitor ") {
+                current = rest.split_whitespace().next().map(str::to_string);
+            } else if let (Some(monitor), Some(rest)) = (&current, trimmed.strip_prefix("active workspace:")) {
+                if let Some(name) = parenthesised(rest) {
+                    self.active.insert(monitor.clone(), name.to_string());
+                }
+            } else if let (Some(monitor), Some(rest)) = (&current, trimmed.strip_prefix("special workspace:")) {
+                if let Some(name) = parenthesised(rest).filter(|n| !n.is_empty()) {
+                    self.special.insert(monitor.clone(), name.to_string());
+                }
+            } else if trimmed == "focused: yes" {
+                self.focused_monitor = current.clone();
+            }
+        }
+    }
+
+    /// Seed fullscreen state from the plain-text `workspaces` reply
+    pub fn seed_workspaces(&mut self, reply: &str) {
+        let mut current: Option<String> = None;
+        for line in reply.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("workspace ID ") {
+                current = parenthesised(trimmed).map(str::to_string);
+            } else if let (Some(workspace), Some(rest)) = (&current, trimmed.strip_prefix("hasfullscreen:")) {
+                self.fullscreen.insert(workspace.clone(), rest.trim() == "1");
+            }
+        }
+    }
+
+    /// Apply a single `EVENT>>DATA` line from `.socket2.sock`
+    pub fn handle_line(&mut self, line: &str) {
+        let Some((event, data)) = line.split_once(">>") else { return };
+
+        match event {
+            "workspace" => {
+                if let Some(monitor) = self.focused_monitor.clone() {
+                    self.active.insert(monitor, data.to_string());
+                }
+            }
+            "focusedmon" => {
+                if let Some((monitor, workspace)) = data.split_once(',') {
+                    self.focused_monitor = Some(monitor.to_string());
+                    self.active.insert(monitor.to_string(), workspace.to_string());
+                }
+            }
+            "moveworkspace" => {
+                if let Some((workspace, monitor)) = data.rsplit_once(',') {
+                    self.active.insert(monitor.to_string(), workspace.to_string());
+                }
+            }
+            "activespecial" => {
+                if let Some((workspace, monitor)) = data.rsplit_once(',') {
+                    if workspace.is_empty() {
+                        self.special.remove(monitor);
+                    } else {
+                        self.special.insert(monitor.to_string(), workspace.to_string());
+                    }
+                }
+            }
+            "fullscreen" => {
+                if let Some(workspace) = self.focused_workspace() {
+                    self.fullscreen.insert(workspace, data == "1");
+                }
+            }
+            "destroyworkspace" => {
+                self.fullscreen.remove(data);
+            }
+            "monitorremoved" => {
+                self.active.remove(data);
+                self.special.remove(data);
+                if self.focused_monitor.as_deref() == Some(data) {
+                    self.focused_monitor = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Events describing what changed for our output since the last call
+    pub fn changes(&mut self) -> Vec<CompositorEvent> {
+        let mut events = Vec::new();
+
+        let workspace = self.workspace_on(&self.display);
+        if workspace.is_some() && workspace != self.last_workspace {
+            events.push(CompositorEvent::Workspace(workspace.clone().unwrap_or_default()));
+            self.last_workspace = workspace.clone();
+        }
+
+        let fullscreen = workspace
+            .as_ref()
+            .and_then(|ws| self.fullscreen.get(ws).copied())
+            .unwrap_or(false);
+        if fullscreen != self.last_fullscreen {
+            events.push(CompositorEvent::Fullscreen(fullscreen));
+            self.last_fullscreen = fullscreen;
+        }
+
+        events
+    }
+
+    fn focused_workspace(&self) -> Option<String> {
+        self.workspace_on(self.focused_monitor.as_deref()?)
+    }
+
+    /// The visible workspace on a monitor, preferring an open special workspace
+    fn workspace_on(&self, monitor: &str) -> Option<String> {
+        let monitor = if self.display == "default" && monitor == "default" {
+            self.focused_monitor.as_deref()?
+        } else {
+            monitor
+        };
+        self.special
+            .get(monitor)
+            .or_else(|| self.active.get(monitor))
+            .cloned()
+    }
+}
+
+/// Extract the text between the first `(` and the following `)`
+fn parenthesised(text: &str) -> Option<&str> {
+    let start = text.find('(')? + 1;
+    let end = start + text[start..].find(')')?;
+    Some(&text[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DisplayConfig;
+    use crate::integrations::CompositorStatus;
+    use crate::test_util::TempDir;
+    use std::os::unix::net::UnixListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    const MONITORS: &str = "\
+Monitor DP-1 (ID 0):
+\t2560x1440@143.99800 at 0x0
+\tactive workspace: 1 (1)
+\tspecial workspace: 0 ()
+\tfocused: yes
+Monitor HDMI-A-1 (ID 1):
+\t1920x1080@60.00000 at 2560x0
+\tactive workspace: 5 (5)
+\tspecial workspace: 0 ()
+\tfocused: no
+";
+
+    const WORKSPACES: &str = "\
+workspace ID 1 (1) on monitor DP-1:
+\tmonitorID: 0
+\twindows: 2
+\thasfullscreen: 0
+workspace ID 5 (5) on monitor HDMI-A-1:
+\tmonitorID: 1
+\twindows: 1
+\thasfullscreen: 1
+";
+
+    /// Recorded from `socat - UNIX-CONNECT:.socket2.sock`, trimmed to what matters
+    const EVENTS: &str = "\
+activewindow>>kitty,~
+workspace>>2
+fullscreen>>1
+focusedmon>>HDMI-A-1,5
+workspace>>6
+focusedmon>>DP-1,2
+activespecial>>special:magic,DP-1
+activespecial>>,DP-1
+fullscreen>>0
+configreloaded>>
+";
+
+    /// Serve `.socket.sock` requests and replay `events` on `.socket2.sock`
+    fn fake_hyprland(dir: &Path, events: &'static str) {
+        let requests = UnixListener::bind(dir.join(".socket.sock")).unwrap();
+        thread::spawn(move || {
+            for stream in requests.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 256];
+                let len = stream.read(&mut request).unwrap();
+                let reply = match &request[..len] {
+                    b"monitors" => MONITORS,
+                    b"workspaces" => WORKSPACES,
+                    b"getoption general:gaps_out" => "custom type: 10 20 10 20\nset: true\n",
+                    _ => "unknown request",
+                };
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        let socket2 = UnixListener::bind(dir.join(".socket2.sock")).unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = socket2.accept().unwrap();
+            stream.write_all(events.as_bytes()).unwrap();
+        });
+    }
+
+    fn events_for(display: &str) -> Vec<CompositorEvent> {
+        let dir = TempDir::new("hyprland");
+        fake_hyprland(dir.path(), EVENTS);
+        let (tx, rx) = mpsc::channel();
+        run(dir.path(), display, true, tx).unwrap();
+        rx.try_iter().collect()
+    }
+
+    #[test]
+    fn replayed_events_follow_our_output() {
+        use CompositorEvent::*;
+        assert_eq!(
+            events_for("DP-1"),
+            vec![
+                Workspace("1".into()),
+                Gaps([10, 20, 10, 20]),
+                Workspace("2".into()),
+                Fullscreen(true),
+                Workspace("special:magic".into()),
+                Fullscreen(false),
+                Workspace("2".into()),
+                Fullscreen(true),
+                Fullscreen(false),
+                Gaps([10, 20, 10, 20]),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_outputs_are_tracked_separately() {
+        use CompositorEvent::*;
+        assert_eq!(
+            events_for("HDMI-A-1"),
+            vec![
+                Workspace("5".into()),
+                Fullscreen(true),
+                Gaps([10, 20, 10, 20]),
+                Workspace("6".into()),
+                Fullscreen(false),
+                Gaps([10, 20, 10, 20]),
+            ]
+        );
+    }
+
+    #[test]
+    fn replayed_events_drive_the_overlay() {
+        let display_config = DisplayConfig {
+            workspace_colors: Some(vec!["2:1f2937".into(), "special:magic:3b2e4a".into()]),
+            hide_on_fullscreen: Some(true),
+            ..DisplayConfig::default()
+        };
+        let mut status = CompositorStatus::default();
+        let mut frames = Vec::new();
+        for event in events_for("DP-1") {
+            status.update(event);
+            frames.push(status.frame_override(&display_config));
+        }
+
+        // Fullscreen on workspace 2 hides the frame
+        assert!(frames[3].hidden);
+        // The special workspace has a colour of its own
+        assert_eq!(frames[4].color.as_deref(), Some("3b2e4a"));
+        assert!(!frames[5].hidden);
+
+        let last = frames.last().unwrap();
+        assert_eq!(last.color.as_deref(), Some("1f2937"));
+        assert_eq!(last.borders, Some([10, 20, 10, 20]));
+        assert!(!last.hidden);
+    }
+}
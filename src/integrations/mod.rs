@@ -0,0 +1,114 @@
+#[cfg(feature = "hyprland")]
+pub mod hyprland;
+#[cfg(feature = "niri")]
+pub mod niri;
+#[cfg(feature = "sway")]
+pub mod sway;
+
+use crate::config::{DisplayConfig, SnugConfig};
+use crate::overlay::FrameOverride;
+use std::sync::mpsc::Sender;
+
+/// State change reported by a compositor integration for our output
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompositorEvent {
+    /// The active workspace on our output changed
+    Workspace(String),
+    /// The active workspace on our output gained or lost a fullscreen window
+    Fullscreen(bool),
+    /// Compositor outer gaps as (top, right, bottom, left)
+    Gaps([i32; 4]),
+    /// The compositor's workspace overview opened or closed
+    Overview(bool),
+    /// The compositor switched binding mode (sway's `resize`, ...)
+    Mode(String),
+    /// Some workspace gained or lost the urgent hint
+    Urgent(bool),
+}
+
+/// Everything the integrations currently know about our output
+#[derive(Debug, Clone, Default)]
+pub struct CompositorStatus {
+    pub workspace: Option<String>,
+    pub fullscreen: bool,
+    pub gaps: Option<[i32; 4]>,
+    pub overview: bool,
+    pub mode: Option<String>,
+    pub urgent: bool,
+    /// Current phase of the urgent flash, toggled by the main loop
+    pub flash_on: bool,
+}
+
+impl CompositorStatus {
+    pub fn update(&mut self, event: CompositorEvent) {
+        match event {
+            CompositorEvent::Workspace(name) => self.workspace = Some(name),
+            CompositorEvent::Fullscreen(fullscreen) => self.fullscreen = fullscreen,
+            CompositorEvent::Gaps(gaps) => self.gaps = Some(gaps),
+            CompositorEvent::Overview(open) => self.overview = open,
+            CompositorEvent::Mode(mode) => self.mode = Some(mode),
+            CompositorEvent::Urgent(urgent) => {
+                self.urgent = urgent;
+                self.flash_on = urgent;
+            }
+        }
+    }
+
+    /// Work out how the frame should differ from the configured one
+    pub fn frame_override(&self, display_config: &DisplayConfig) -> FrameOverride {
+        let urgent_color = display_config.urgent_color.as_deref().filter(|_| self.urgent && self.flash_on);
+        let mode_color = self.mode.as_deref().and_then(|mode| display_config.mode_color(mode));
+        let workspace_color = self.workspace.as_deref().and_then(|ws| display_config.workspace_color(ws));
+
+        FrameOverride {
+            color: urgent_color.or(mode_color).or(workspace_color).map(str::to_string),
+            opacity: display_config.overview_opacity.filter(|_| self.overview),
+            radius: None,
+            borders: self.gaps,
+            hidden: self.fullscreen && display_config.hide_on_fullscreen.unwrap_or(false),
+        }
+    }
+}
+
+/// Start whichever compositor integrations apply to this session
+pub fn spawn(snug_config: &SnugConfig, display: &str, tx: Sender<CompositorEvent>) {
+    #[cfg(feature = "hyprland")]
+    if snug_config.hyprland.enabled && let Some(dir) = hyprland::socket_dir() {
+        tracing::info!("Hyprland detected, listening for events");
+        let display = display.to_string();
+        let sync_gaps = snug_config.hyprland.sync_gaps;
+        let tx = tx.clone();
+        crate::logging::spawn(move || {
+            if let Err(e) = hyprland::run(&dir, &display, sync_gaps, tx) {
+                tracing::warn!("Hyprland IPC stopped: {}", e);
+            }
+        });
+    }
+
+    #[cfg(feature = "niri")]
+    if snug_config.niri.enabled && let Some(socket) = niri::socket_path() {
+        tracing::info!("niri detected, listening for events");
+        let display = display.to_string();
+        let tx = tx.clone();
+        crate::logging::spawn(move || {
+            if let Err(e) = niri::run(&socket, &display, tx) {
+                tracing::warn!("niri IPC stopped: {}", e);
+            }
+        });
+    }
+
+    #[cfg(feature = "sway")]
+    if snug_config.sway.enabled && let Some(socket) = sway::socket_path() {
+        tracing::info!("sway/i3 detected, listening for events");
+        let display = display.to_string();
+        let tx = tx.clone();
+        crate::logging::spawn(move || {
+            if let Err(e) = sway::run(&socket, &display, tx) {
+                tracing::warn!("i3-IPC stopped: {}", e);
+            }
+        });
+    }
+
+    #[cfg(not(any(feature = "hyprland", feature = "niri", feature = "sway")))]
+    let _ = (snug_config, display, tx);
+}
@@ -0,0 +1,326 @@
+//! Niri IPC integration
+//!
+//! Niri speaks newline-delimited JSON on `$NIRI_SOCKET`. A request is a single
+//! JSON value (`"EventStream"`, `"Outputs"`, ...) answered by one reply line;
+//! after `"EventStream"` the socket keeps streaming events.
+
+use super::CompositorEvent;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+/// Path of the running niri instance's IPC socket
+pub fn socket_path() -> Option<PathBuf> {
+    env::var_os("NIRI_SOCKET")
+        .map(PathBuf::from)
+        .filter(|path| path.exists())
+}
+
+/// Send a request and return the `Ok` payload of the reply
+pub fn request(socket: &Path, request: &str) -> std::io::Result<Value> {
+    let mut stream = UnixStream::connect(socket)?;
+    writeln!(stream, "\"{}\"", request)?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+
+    let reply: Value = serde_json::from_str(&reply)?;
+    match reply.get("Ok") {
+        Some(payload) => Ok(payload.clone()),
+        None => Err(std::io::Error::other(format!("niri refused {}: {}", request, reply))),
+    }
+}
+
+/// Listen for events until the socket closes, forwarding changes for `display`
+pub fn run(socket: &Path, display: &str, tx: Sender<CompositorEvent>) -> std::io::Result<()> {
+    let mut tracker = Tracker::new(display);
+    if let Ok(outputs) = request(socket, "Outputs") {
+        tracker.seed_outputs(&outputs);
+    }
+
+    let mut stream = UnixStream::connect(socket)?;
+    writeln!(stream, "\"EventStream\"")?;
+    let mut lines = BufReader::new(stream).lines();
+
+    // The first line acknowledges the request, events follow
+    if let Some(ack) = lines.next() {
+        let ack: Value = serde_json::from_str(&ack?)?;
+        if ack.get("Ok").is_none() {
+            return Err(std::io::Error::other(format!("niri refused EventStream: {}", ack)));
+        }
+    }
+
+    for line in lines {
+        let line = line?;
+        let Ok(event) = serde_json::from_str::<Value>(&line) else { continue };
+        tracker.handle_event(&event);
+        for event in tracker.changes() {
+            if tx.send(event).is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct Workspace {
+    name: String,
+    output: Option<String>,
+    is_active: bool,
+    active_window_id: Option<u64>,
+}
+
+/// Follows niri's workspace, window and overview state for one output
+pub struct Tracker {
+    display: String,
+    output_size: Option<(f64, f64)>,
+    workspaces: HashMap<u64, Workspace>,
+    window_sizes: HashMap<u64, (f64, f64)>,
+    overview_open: bool,
+    last_workspace: Option<String>,
+    last_fullscreen: bool,
+    last_overview: bool,
+}
+
+impl Tracker {
+    pub fn new(display: &str) -> Self {
+        Self {
+            display: display.to_string(),
+            output_size: None,
+            workspaces: HashMap::new(),
+            window_sizes: HashMap::new(),
+            overview_open: false,
+            last_workspace: None,
+            last_fullscreen: false,
+            last_overview: false,
+        }
+    }
+
+    /// Record our output's logical size from an `Outputs` reply
+    pub fn seed_outputs(&mut self, outputs: &Value) {
+        let outputs = outputs.get("Outputs").unwrap_or(outputs);
+        let Some(outputs) = outputs.as_object() else { return };
+        let output = if self.display == "default" {
+            outputs.values().next()
+        } else {
+            outputs.get(&self.display)
+        };
+        if let Some(logical) = output.and_then(|o| o.get("logical")) {
+            let width = logical.get("width").and_then(Value::as_f64);
+            let height = logical.get("height").and_then(Value::as_f64);
+            if let (Some(width), Some(height)) = (width, height) {
+                self.output_size = Some((width, height));
+            }
+        }
+    }
+
+    /// Apply one event object from the event stream
+    pub fn handle_event(&mut self, event: &Value) {
+        let Some((kind, body)) = event.as_object().and_then(|o| o.iter().next()) else { return };
+
+        match kind.as_str() {
+            "WorkspacesChanged" => {
+                self.workspaces.clear();
+                for ws in body.get("workspaces").and_then(Value::as_array).into_iter().flatten() {
+                    if let Some((id, workspace)) = parse_workspace(ws) {
+                        self.workspaces.insert(id, workspace);
+                    }
+                }
+            }
+            "WorkspaceActivated" => {
+                let Some(id) = body.get("id").and_then(Value::as_u64) else { return };
+                let output = self.workspaces.get(&id).and_then(|ws| ws.output.clone());
+                for (ws_id, ws) in self.workspaces.iter_mut() {
+                    if ws.output == output {
+                        ws.is_active = *ws_id == id;
+                    }
+                }
+            }
+            "WorkspaceActiveWindowChanged" => {
+                let Some(id) = body.get("workspace_id").and_then(Value::as_u64) else { return };
+                if let Some(ws) = self.workspaces.get_mut(&id) {
+                    ws.active_window_id = body.get("active_window_id").and_then(Value::as_u64);
+                }
+            }
+            "WindowsChanged" => {
+                self.window_sizes.clear();
+                for window in body.get("windows").and_then(Value::as_array).into_iter().flatten() {
+                    self.update_window(window);
+                }
+            }
+            "WindowOpenedOrChanged" => {
+                if let Some(window) = body.get("window") {
+                    self.update_window(window);
+                }
+            }
+            "WindowClosed" => {
+                if let Some(id) = body.get("id").and_then(Value::as_u64) {
+                    self.window_sizes.remove(&id);
+                }
+            }
+            "WindowLayoutsChanged" => {
+                for change in body.get("changes").and_then(Value::as_array).into_iter().flatten() {
+                    let id = change.get(0).and_then(Value::as_u64);
+                    let size = change.get(1).and_then(tile_size);
+                    if let (Some(id), Some(size)) = (id, size) {
+                        self.window_sizes.insert(id, size);
+                    }
+                }
+            }
+            "OverviewOpenedOrClosed" => {
+                self.overview_open = body.get("is_open").and_then(Value::as_bool).unwrap_or(false);
+            }
+            _ => {}
+        }
+    }
+
+    /// Events describing what changed for our output since the last call
+    pub fn changes(&mut self) -> Vec<CompositorEvent> {
+        let mut events = Vec::new();
+
+        let active = self.workspaces.values().find(|ws| {
+            ws.is_active && (self.display == "default" || ws.output.as_deref() == Some(self.display.as_str()))
+        });
+
+        let workspace = active.map(|ws| ws.name.clone());
+        if workspace.is_some() && workspace != self.last_workspace {
+            events.push(CompositorEvent::Workspace(workspace.clone().unwrap_or_default()));
+            self.last_workspace = workspace;
+        }
+
+        // niri has no fullscreen flag in IPC, a fullscreen column fills the whole output
+        let fullscreen = match (active.and_then(|ws| ws.active_window_id), self.output_size) {
+            (Some(window), Some((out_w, out_h))) => self
+                .window_sizes
+                .get(&window)
+                .is_some_and(|&(w, h)| w >= out_w && h >= out_h),
+            _ => false,
+        };
+        if fullscreen != self.last_fullscreen {
+            events.push(CompositorEvent::Fullscreen(fullscreen));
+            self.last_fullscreen = fullscreen;
+        }
+
+        if self.overview_open != self.last_overview {
+            events.push(CompositorEvent::Overview(self.overview_open));
+            self.last_overview = self.overview_open;
+        }
+
+        events
+    }
+
+    fn update_window(&mut self, window: &Value) {
+        let id = window.get("id").and_then(Value::as_u64);
+        let size = window.get("layout").and_then(tile_size);
+        if let (Some(id), Some(size)) = (id, size) {
+            self.window_sizes.insert(id, size);
+        }
+    }
+}
+
+/// Workspaces are matched by name when they have one, otherwise by index
+fn parse_workspace(ws: &Value) -> Option<(u64, Workspace)> {
+    let id = ws.get("id")?.as_u64()?;
+    let name = match ws.get("name").and_then(Value::as_str) {
+        Some(name) => name.to_string(),
+        None => ws.get("idx")?.as_u64()?.to_string(),
+    };
+    Some((id, Workspace {
+        name,
+        output: ws.get("output").and_then(Value::as_str).map(str::to_string),
+        is_active: ws.get("is_active").and_then(Value::as_bool).unwrap_or(false),
+        active_window_id: ws.get("active_window_id").and_then(Value::as_u64),
+    }))
+}
+
+fn tile_size(layout: &Value) -> Option<(f64, f64)> {
+    let size = layout.get("tile_size")?;
+    Some((size.get(0)?.as_f64()?, size.get(1)?.as_f64()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::os::unix::net::UnixListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    const OUTPUTS: &str = r#"{"Ok":{"Outputs":{"DP-1":{"name":"DP-1","logical":{"x":0,"y":0,"width":2560,"height":1440,"scale":1.0}},"HDMI-A-1":{"name":"HDMI-A-1","logical":{"x":2560,"y":0,"width":1920,"height":1080,"scale":1.0}}}}}"#;
+
+    /// Event stream lines as niri 25.x sends them, one event per line
+    const EVENTS: &[&str] = &[
+        r#"{"WorkspacesChanged":{"workspaces":[{"id":1,"idx":1,"name":null,"output":"DP-1","is_urgent":false,"is_active":true,"is_focused":true,"active_window_id":null},{"id":2,"idx":2,"name":null,"output":"DP-1","is_urgent":false,"is_active":false,"is_focused":false,"active_window_id":null},{"id":3,"idx":1,"name":"chat","output":"HDMI-A-1","is_urgent":false,"is_active":true,"is_focused":false,"active_window_id":null}]}}"#,
+        r#"{"WorkspaceActivated":{"id":2,"focused":true}}"#,
+        r#"{"WindowOpenedOrChanged":{"window":{"id":7,"title":"mpv","app_id":"mpv","workspace_id":2,"is_focused":true,"is_floating":false,"layout":{"tile_size":[2560.0,1440.0],"window_size":[2560,1440]}}}}"#,
+        r#"{"WorkspaceActiveWindowChanged":{"workspace_id":2,"active_window_id":7}}"#,
+        r#"{"OverviewOpenedOrClosed":{"is_open":true}}"#,
+        r#"{"OverviewOpenedOrClosed":{"is_open":false}}"#,
+        r#"{"WindowOpenedOrChanged":{"window":{"id":7,"title":"mpv","app_id":"mpv","workspace_id":2,"is_focused":true,"is_floating":false,"layout":{"tile_size":[1270.0,1440.0],"window_size":[1270,1440]}}}}"#,
+        "not json",
+        r#"{"WorkspaceActivated":{"id":3,"focused":false}}"#,
+    ];
+
+    /// Answer `"Outputs"`, then stream `EVENTS` to the `"EventStream"` connection
+    fn fake_niri(socket: &Path) {
+        let listener = UnixListener::bind(socket).unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                BufReader::new(&stream).read_line(&mut request).unwrap();
+                match request.trim() {
+                    "\"Outputs\"" => writeln!(stream, "{}", OUTPUTS).unwrap(),
+                    "\"EventStream\"" => {
+                        writeln!(stream, r#"{{"Ok":"Handled"}}"#).unwrap();
+                        for line in EVENTS {
+                            writeln!(stream, "{}", line).unwrap();
+                        }
+                        return;
+                    }
+                    _ => writeln!(stream, r#"{{"Err":"unknown request"}}"#).unwrap(),
+                }
+            }
+        });
+    }
+
+    fn events_for(display: &str) -> Vec<CompositorEvent> {
+        let dir = TempDir::new("niri");
+        let socket = dir.path().join("niri.sock");
+        fake_niri(&socket);
+        let (tx, rx) = mpsc::channel();
+        run(&socket, display, tx).unwrap();
+        rx.try_iter().collect()
+    }
+
+    #[test]
+    fn event_stream_follows_our_output() {
+        use CompositorEvent::*;
+        assert_eq!(
+            events_for("DP-1"),
+            vec![
+                Workspace("1".into()),
+                Workspace("2".into()),
+                // The window filled the output once it became the active one
+                Fullscreen(true),
+                Overview(true),
+                Overview(false),
+                Fullscreen(false),
+            ]
+        );
+    }
+
+    #[test]
+    fn named_workspaces_on_other_outputs() {
+        use CompositorEvent::*;
+        assert_eq!(
+            events_for("HDMI-A-1"),
+            vec![Workspace("chat".into()), Overview(true), Overview(false)]
+        );
+    }
+}
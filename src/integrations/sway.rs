@@ -0,0 +1,357 @@
+//! sway / i3 IPC integration
+//!
+//! Messages on the i3-IPC socket are framed as the magic string `i3-ipc`, a
+//! native-endian payload length and message type, then a JSON payload. Events
+//! use the same framing with the high bit set on the type.
+
+use super::CompositorEvent;
+use serde_json::Value;
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+const MAGIC: &[u8] = b"i3-ipc";
+
+const GET_WORKSPACES: u32 = 1;
+const SUBSCRIBE: u32 = 2;
+const GET_TREE: u32 = 4;
+
+const EVENT_WORKSPACE: u32 = 0x8000_0000;
+const EVENT_MODE: u32 = 0x8000_0002;
+const EVENT_WINDOW: u32 = 0x8000_0003;
+
+/// Path of the running sway (or i3) instance's IPC socket
+pub fn socket_path() -> Option<PathBuf> {
+    ["SWAYSOCK", "I3SOCK"]
+        .iter()
+        .filter_map(env::var_os)
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+}
+
+/// Write one framed message
+pub fn write_message(stream: &mut impl Write, kind: u32, payload: &[u8]) -> std::io::Result<()> {
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream.write_all(&message)
+}
+
+/// Read one framed message, returning its type and JSON payload
+pub fn read_message(stream: &mut impl Read) -> std::io::Result<(u32, Value)> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        return Err(std::io::Error::other("bad i3-ipc magic"));
+    }
+    let length = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let kind = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload)?;
+    Ok((kind, serde_json::from_slice(&payload)?))
+}
+
+/// Send a request and wait for its reply
+pub fn request(stream: &mut UnixStream, kind: u32) -> std::io::Result<Value> {
+    write_message(stream, kind, b"")?;
+    loop {
+        let (reply_kind, payload) = read_message(stream)?;
+        if reply_kind == kind {
+            return Ok(payload);
+        }
+    }
+}
+
+/// Listen for events until the socket closes, forwarding changes for `display`
+pub fn run(socket: &Path, display: &str, tx: Sender<CompositorEvent>) -> std::io::Result<()> {
+    let mut requests = UnixStream::connect(socket)?;
+    let mut events = UnixStream::connect(socket)?;
+
+    write_message(&mut events, SUBSCRIBE, br#"["workspace","mode","window"]"#)?;
+    let (_, reply) = read_message(&mut events)?;
+    if reply.get("success").and_then(Value::as_bool) != Some(true) {
+        return Err(std::io::Error::other(format!("subscribe failed: {}", reply)));
+    }
+
+    let mut tracker = Tracker::new(display);
+    tracker.refresh_workspaces(&request(&mut requests, GET_WORKSPACES)?);
+    tracker.refresh_tree(&request(&mut requests, GET_TREE)?);
+
+    loop {
+        for event in tracker.changes() {
+            if tx.send(event).is_err() {
+                return Ok(());
+            }
+        }
+
+        let (kind, payload) = read_message(&mut events)?;
+        match kind {
+            EVENT_WORKSPACE => {
+                tracker.refresh_workspaces(&request(&mut requests, GET_WORKSPACES)?);
+                tracker.refresh_tree(&request(&mut requests, GET_TREE)?);
+            }
+            EVENT_MODE => {
+                if let Some(mode) = payload.get("change").and_then(Value::as_str) {
+                    tracker.mode = mode.to_string();
+                }
+            }
+            EVENT_WINDOW => {
+                let change = payload.get("change").and_then(Value::as_str).unwrap_or("");
+                if change == "urgent" {
+                    tracker.refresh_workspaces(&request(&mut requests, GET_WORKSPACES)?);
+                }
+                if matches!(change, "fullscreen_mode" | "new" | "close" | "move" | "focus") {
+                    tracker.refresh_tree(&request(&mut requests, GET_TREE)?);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Follows sway's workspace, mode and fullscreen state for one output
+pub struct Tracker {
+    display: String,
+    workspace: Option<String>,
+    fullscreen: bool,
+    urgent: bool,
+    mode: String,
+    last_workspace: Option<String>,
+    last_fullscreen: bool,
+    last_urgent: bool,
+    last_mode: String,
+}
+
+impl Tracker {
+    pub fn new(display: &str) -> Self {
+        Self {
+            display: display.to_string(),
+            workspace: None,
+            fullscreen: false,
+            urgent: false,
+            mode: "default".to_string(),
+            last_workspace: None,
+            last_fullscreen: false,
+            last_urgent: false,
+            last_mode: "default".to_string(),
+        }
+    }
+
+    fn on_our_output(&self, output: Option<&str>, focused: bool) -> bool {
+        if self.display == "default" {
+            focused
+        } else {
+            output == Some(self.display.as_str())
+        }
+    }
+
+    /// Update the visible workspace and urgency from a `GET_WORKSPACES` reply
+    pub fn refresh_workspaces(&mut self, workspaces: &Value) {
+        let workspaces = workspaces.as_array().map(Vec::as_slice).unwrap_or_default();
+
+        self.urgent = workspaces
+            .iter()
+            .any(|ws| ws.get("urgent").and_then(Value::as_bool).unwrap_or(false));
+
+        let visible = workspaces.iter().find(|ws| {
+            let output = ws.get("output").and_then(Value::as_str);
+            let focused = ws.get("focused").and_then(Value::as_bool).unwrap_or(false);
+            let visible = ws.get("visible").and_then(Value::as_bool).unwrap_or(false);
+            visible && self.on_our_output(output, focused)
+        });
+        if let Some(name) = visible.and_then(|ws| ws.get("name")).and_then(Value::as_str) {
+            self.workspace = Some(name.to_string());
+        }
+    }
+
+    /// Check the visible workspace for a fullscreen container in a `GET_TREE` reply
+    pub fn refresh_tree(&mut self, tree: &Value) {
+        let Some(workspace) = self.workspace.clone() else { return };
+        self.fullscreen = find_workspace(tree, &workspace).is_some_and(has_fullscreen);
+    }
+
+    /// Events describing what changed since the last call
+    pub fn changes(&mut self) -> Vec<CompositorEvent> {
+        let mut events = Vec::new();
+
+        if self.workspace.is_some() && self.workspace != self.last_workspace {
+            events.push(CompositorEvent::Workspace(self.workspace.clone().unwrap_or_default()));
+            self.last_workspace = self.workspace.clone();
+        }
+        if self.fullscreen != self.last_fullscreen {
+            events.push(CompositorEvent::Fullscreen(self.fullscreen));
+            self.last_fullscreen = self.fullscreen;
+        }
+        if self.urgent != self.last_urgent {
+            events.push(CompositorEvent::Urgent(self.urgent));
+            self.last_urgent = self.urgent;
+        }
+        if self.mode != self.last_mode {
+            events.push(CompositorEvent::Mode(self.mode.clone()));
+            self.last_mode = self.mode.clone();
+        }
+
+        events
+    }
+}
+
+fn children(node: &Value) -> impl Iterator<Item = &Value> {
+    ["nodes", "floating_nodes"]
+        .into_iter()
+        .filter_map(|key| node.get(key).and_then(Value::as_array))
+        .flatten()
+}
+
+fn find_workspace<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    let is_workspace = node.get("type").and_then(Value::as_str) == Some("workspace");
+    if is_workspace && node.get("name").and_then(Value::as_str) == Some(name) {
+        return Some(node);
+    }
+    children(node).find_map(|child| find_workspace(child, name))
+}
+
+fn has_fullscreen(node: &Value) -> bool {
+    node.get("fullscreen_mode").and_then(Value::as_u64).unwrap_or(0) != 0
+        || children(node).any(has_fullscreen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use serde_json::json;
+    use std::collections::VecDeque;
+    use std::io::ErrorKind;
+    use std::os::unix::net::UnixListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn framing_round_trips() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, SUBSCRIBE, br#"["workspace"]"#).unwrap();
+        assert_eq!(&buf[..6], MAGIC);
+        assert_eq!(buf[6..10], 13u32.to_ne_bytes());
+        assert_eq!(buf[10..14], SUBSCRIBE.to_ne_bytes());
+
+        let (kind, payload) = read_message(&mut buf.as_slice()).unwrap();
+        assert_eq!(kind, SUBSCRIBE);
+        assert_eq!(payload, json!(["workspace"]));
+    }
+
+    #[test]
+    fn truncated_or_foreign_headers_are_errors() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, GET_TREE, b"{}").unwrap();
+
+        let error = read_message(&mut &buf[..9]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        // Header intact, payload cut short
+        let error = read_message(&mut &buf[..15]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        buf[..6].copy_from_slice(b"i4-ipc");
+        assert!(read_message(&mut buf.as_slice()).is_err());
+    }
+
+    fn workspace(name: &str, output: &str, visible: bool, urgent: bool) -> Value {
+        json!({"name": name, "output": output, "visible": visible, "focused": visible, "urgent": urgent})
+    }
+
+    fn tree(workspace: &str, fullscreen: bool) -> Value {
+        json!({"type": "root", "nodes": [{"type": "output", "name": "DP-1", "nodes": [
+            {"type": "workspace", "name": workspace, "nodes": [
+                {"type": "con", "name": "mpv", "fullscreen_mode": u64::from(fullscreen), "nodes": []}
+            ], "floating_nodes": []}
+        ]}]})
+    }
+
+    /// Read a request's type; unlike replies, requests may have no payload
+    fn read_request(stream: &mut UnixStream) -> std::io::Result<u32> {
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header)?;
+        assert_eq!(&header[..6], MAGIC);
+        let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+        let mut payload = vec![0u8; length as usize];
+        stream.read_exact(&mut payload)?;
+        Ok(u32::from_ne_bytes(header[10..14].try_into().unwrap()))
+    }
+
+    /// An i3-IPC server answering requests from `workspaces` and `trees` in
+    /// order, and sending `events` to the subscribed connection
+    fn fake_sway(socket: &Path, mut workspaces: VecDeque<Value>, mut trees: VecDeque<Value>, events: Vec<(u32, Value)>) {
+        let listener = UnixListener::bind(socket).unwrap();
+        thread::spawn(move || {
+            // `run` connects for requests first, then for events
+            let (mut requests, _) = listener.accept().unwrap();
+            let (mut subscribed, _) = listener.accept().unwrap();
+
+            thread::spawn(move || {
+                while let Ok(kind) = read_request(&mut requests) {
+                    let reply = match kind {
+                        GET_WORKSPACES => workspaces.pop_front().unwrap(),
+                        GET_TREE => trees.pop_front().unwrap(),
+                        _ => json!({"success": false}),
+                    };
+                    write_message(&mut requests, kind, reply.to_string().as_bytes()).unwrap();
+                }
+            });
+
+            let (kind, events_wanted) = read_message(&mut subscribed).unwrap();
+            assert_eq!(kind, SUBSCRIBE);
+            assert_eq!(events_wanted, json!(["workspace", "mode", "window"]));
+            write_message(&mut subscribed, SUBSCRIBE, br#"{"success":true}"#).unwrap();
+            for (kind, payload) in events {
+                write_message(&mut subscribed, kind, payload.to_string().as_bytes()).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn events_drive_the_tracker() {
+        let dir = TempDir::new("sway");
+        let socket = dir.path().join("sway-ipc.sock");
+        let hdmi = workspace("5", "HDMI-A-1", true, false);
+        fake_sway(
+            &socket,
+            VecDeque::from([
+                json!([workspace("1", "DP-1", true, false), hdmi.clone()]),
+                json!([workspace("2", "DP-1", true, false), hdmi.clone()]),
+                json!([workspace("2", "DP-1", true, false), workspace("3", "DP-1", false, true), hdmi]),
+            ]),
+            VecDeque::from([tree("1", false), tree("2", true), tree("2", false)]),
+            vec![
+                (EVENT_WORKSPACE, json!({"change": "focus"})),
+                (EVENT_MODE, json!({"change": "resize"})),
+                (EVENT_WINDOW, json!({"change": "urgent"})),
+                (EVENT_WINDOW, json!({"change": "fullscreen_mode"})),
+                (EVENT_MODE, json!({"change": "default"})),
+                (EVENT_WINDOW, json!({"change": "title"})),
+            ],
+        );
+
+        let (tx, rx) = mpsc::channel();
+        // The server hangs up after the last event
+        let error = run(&socket, "DP-1", tx).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        use CompositorEvent::*;
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![
+                Workspace("1".into()),
+                Workspace("2".into()),
+                Fullscreen(true),
+                Mode("resize".into()),
+                Urgent(true),
+                Fullscreen(false),
+                Mode("default".into()),
+            ]
+        );
+    }
+}
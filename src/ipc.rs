@@ -0,0 +1,159 @@
+use crate::args::MsgAction;
+use crate::logging;
+use crate::paths::runtime_dir;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+use tracing::warn;
+use std::os::unix::fs::FileTypeExt;
+
+/// Control socket of the child process serving `display_name`
+pub fn socket_path(display_name: &str) -> PathBuf {
+    runtime_dir().join(format!("snug-{}.sock", display_name))
+}
+
+/// The display a control socket file name such as `snug-DP-1.sock` belongs to
+fn display_of_socket(file_name: &str) -> Option<&str> {
+    let display = file_name.strip_prefix("snug-")?.strip_suffix(".sock")?;
+    (!display.is_empty()).then_some(display)
+}
+
+/// Encode an action as a single JSON line for the control socket
+pub fn encode(action: &MsgAction) -> String {
+    serde_json::to_string(action).expect("actions always serialize")
+}
+
+/// Decode a line received on the control socket
+pub fn decode(line: &str) -> Result<MsgAction, String> {
+    serde_json::from_str(line).map_err(|e| format!("invalid message '{}': {}", line, e))
+}
+
+/// Listen on the display's control socket and forward decoded actions
+pub fn spawn_listener(display_name: &str, tx: Sender<MsgAction>) -> std::io::Result<()> {
+    let path = socket_path(display_name);
+    // We hold the display lock, so any existing socket is stale
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+
+    logging::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut line = String::new();
+            if BufReader::new(&stream).read_line(&mut line).is_err() {
+                continue;
+            }
+
+            let reply = match decode(line.trim()) {
+                Ok(action) => {
+                    if tx.send(action).is_err() {
+                        break;
+                    }
+                    "ok".to_string()
+                }
+                Err(e) => {
+                    warn!("Ignoring control message: {}", e);
+                    format!("error: {}", e)
+                }
+            };
+            let _ = writeln!(stream, "{}", reply);
+        }
+    });
+
+    Ok(())
+}
+
+/// Send an action to one display's instance, or to every running instance
+pub fn send_message(display_name: Option<&str>, action: &MsgAction) -> Result<(), Box<dyn std::error::Error>> {
+    let sockets: Vec<(String, PathBuf)> = match display_name {
+        Some(name) => vec![(name.to_string(), socket_path(name))],
+        None => fs::read_dir(runtime_dir())?
+            .filter_map(|entry| entry.ok())
+            // Only control sockets; lock files and directories share the prefix
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_socket()))
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let display = display_of_socket(&file_name)?.to_string();
+                Some((display, entry.path()))
+            })
+            .collect(),
+    };
+
+    if sockets.is_empty() {
+        return Err("no running snug instances found".into());
+    }
+
+    let line = encode(action);
+    let mut delivered = 0;
+    for (display, path) in sockets {
+        match deliver(&path, &line) {
+            Ok(reply) if reply == "ok" => delivered += 1,
+            Ok(reply) => eprintln!("[{}] {}", display, reply),
+            Err(e) => eprintln!("[{}] Could not reach instance: {}", display, e),
+        }
+    }
+
+    if delivered == 0 {
+        return Err("message was not delivered to any instance".into());
+    }
+    Ok(())
+}
+
+fn deliver(path: &PathBuf, line: &str) -> std::io::Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    writeln!(stream, "{}", line)?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_round_trip_with_spaces() {
+        let actions = [
+            MsgAction::Flash {
+                color: "rgb(255, 0, 0)".to_string(),
+                duration: Duration::from_millis(400),
+            },
+            MsgAction::Pulse {
+                color: Some("hsl(0 100% 50%)".to_string()),
+                opacity: Some(0.25),
+                duration: Duration::from_millis(1500),
+                count: 3,
+            },
+            MsgAction::Pulse {
+                color: None,
+                opacity: None,
+                duration: Duration::from_secs(1),
+                count: 1,
+            },
+            MsgAction::Profile { name: Some("late night".to_string()) },
+            MsgAction::Profile { name: None },
+        ];
+        for action in actions {
+            let line = encode(&action);
+            assert!(!line.contains('\n'), "{}", line);
+            assert_eq!(decode(&line), Ok(action));
+        }
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        for line in ["", "flash ff0000 400", r#"{"action":"explode"}"#, r#"{"action":"pulse"}"#] {
+            assert!(decode(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn socket_names_map_to_displays() {
+        assert_eq!(display_of_socket("snug-DP-1.sock"), Some("DP-1"));
+        assert_eq!(display_of_socket("snug-.sock"), None);
+        assert_eq!(display_of_socket("snug-DP-1.lock"), None);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::args::Args;
+use crate::paths::expand_tilde;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use tracing::Span;
+use tracing_subscriber::EnvFilter;
+
+/// Filter directives overriding `-v`/`-q`, e.g. `SNUG_LOG=snug::app=trace,info`
+pub const LOG_ENV: &str = "SNUG_LOG";
+
+/// Install the global logger from `$SNUG_LOG`, `-v`/`-q` and `--log-file`
+///
+/// Without `$SNUG_LOG` snug logs at info and its dependencies at warn.
+pub fn init(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let default = || EnvFilter::new(format!("warn,snug={}", level(args)));
+    let filter = match env::var(LOG_ENV) {
+        Ok(directives) => EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+            // The logger isn't up yet
+            eprintln!("Ignoring ${}: {}", LOG_ENV, e);
+            default()
+        }),
+        Err(_) => default(),
+    };
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match &args.log_file {
+        Some(path) => {
+            let path = expand_tilde(path);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| format!("Could not open log file {}: {}", path.display(), e))?;
+            builder.with_ansi(false).with_writer(Mutex::new(file)).init();
+        }
+        None if io::stderr().is_terminal() => builder.with_writer(io::stderr).init(),
+        // journald and other collectors stamp lines themselves
+        None => builder.with_writer(io::stderr).with_ansi(false).without_time().init(),
+    }
+    Ok(())
+}
+
+fn level(args: &Args) -> &'static str {
+    match i16::from(args.verbose) - i16::from(args.quiet) {
+        ..=-2 => "error",
+        -1 => "warn",
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    }
+}
+
+/// `thread::spawn`, logging under the caller's span so the display field carries over
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let span = Span::current();
+    thread::spawn(move || span.in_scope(f))
+}
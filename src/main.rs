@@ -1,18 +1,59 @@
+mod animation;
 mod app;
 mod args;
+mod check;
 mod colour;
 mod config;
+mod config_set;
+mod config_source;
 mod drawing;
+mod dump;
+mod external;
 mod handlers;
+mod indicator;
+mod integrations;
+mod ipc;
+mod logging;
+mod notify;
+mod overlay;
+mod palette;
+mod paths;
+mod power;
 mod process;
+mod schedule;
+mod solid;
+mod swapchain;
+mod wal;
+mod watcher;
 mod wayland;
 mod event_loop;
+#[cfg(test)]
+mod test_util;
 
-use args::Args;
+use args::{Args, Command, MsgAction};
 use clap::Parser;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    logging::init(&args)?;
+    
+    if let Some(Command::Msg { display, action }) = &args.command {
+        if let MsgAction::Profile { name: Some(name) } = action {
+            config::validate_profile(&args, name)?;
+        }
+        return ipc::send_message(display.as_deref(), action);
+    }
+    
+    if let Some(Command::Config { action }) = &args.command {
+        return config::run_config_command(action, &args);
+    }
+    
+    if let Some(Command::Check { path }) = &args.command {
+        if !check::run(path.as_deref())? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     
     if args.display.is_none() {
         process::spawn_child_processes(args)
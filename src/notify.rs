@@ -0,0 +1,323 @@
+use crate::logging;
+use crate::paths::runtime_dir;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Where systemd listens for state changes of a `Type=notify` service
+pub const NOTIFY_ENV: &str = "NOTIFY_SOCKET";
+
+/// Directory for the relay's sockets, kept apart from the `snug-*.sock` control sockets
+const RELAY_DIR: &str = "snug-notify";
+
+/// How often the parent looks at its children when the watchdog is off
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Send `state`, newline-separated `KEY=value` pairs, to `$NOTIFY_SOCKET`
+///
+/// Does nothing when snug isn't run by systemd, or by a parent relaying for it.
+pub fn notify(state: &str) {
+    let Some(socket) = env::var_os(NOTIFY_ENV) else { return };
+    if let Err(e) = send(&socket.to_string_lossy(), state) {
+        debug!("Could not notify {}: {}", socket.to_string_lossy(), e);
+    }
+}
+
+fn send(socket: &str, state: &str) -> io::Result<()> {
+    // A leading @ names a socket in the abstract namespace
+    let address = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+/// Watchdog timeout systemd expects this process to ping within
+fn watchdog_timeout() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    // Set by systemd for the main process only; a relaying parent removes it
+    if let Ok(pid) = env::var("WATCHDOG_PID")
+        && pid.parse() != Ok(std::process::id())
+    {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+/// Pings the watchdog from the event loop, so a hung loop gets restarted
+pub struct Watchdog {
+    interval: Option<Duration>,
+    next_ping: Instant,
+}
+
+impl Watchdog {
+    pub fn from_env() -> Self {
+        Self {
+            // Well inside the timeout, so a relaying parent always has a fresh ping
+            interval: watchdog_timeout().map(|timeout| timeout / 4),
+            next_ping: Instant::now(),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        let Some(interval) = self.interval else { return };
+        let now = Instant::now();
+        if now >= self.next_ping {
+            notify("WATCHDOG=1");
+            self.next_ping = now + interval;
+        }
+    }
+}
+
+/// What the parent knows about one display's instance
+struct Instance {
+    display: String,
+    child: Child,
+    ready: bool,
+    /// Pinged the watchdog since the parent last did
+    pinged: bool,
+    status: Option<String>,
+    exited: bool,
+}
+
+/// Stands in for systemd towards the per-display children
+///
+/// Each child gets its own socket as `$NOTIFY_SOCKET`, so the parent knows
+/// who sent what. The service is ready once every instance has drawn (or
+/// given up), and the watchdog is only pinged while every instance is.
+pub struct Relay {
+    sockets: Vec<PathBuf>,
+    tx: Sender<(String, String)>,
+    rx: Receiver<(String, String)>,
+    instances: Vec<Instance>,
+    watchdog: Option<Duration>,
+}
+
+impl Relay {
+    /// A relay when running under systemd, `None` otherwise
+    pub fn from_env() -> Option<Self> {
+        env::var_os(NOTIFY_ENV)?;
+        let (tx, rx) = mpsc::channel();
+        Some(Self {
+            sockets: Vec::new(),
+            tx,
+            rx,
+            instances: Vec::new(),
+            watchdog: watchdog_timeout(),
+        })
+    }
+
+    /// Point a child's notifications at a socket of its own
+    pub fn prepare(&mut self, cmd: &mut Command, display: &str) -> io::Result<()> {
+        let dir = runtime_dir().join(RELAY_DIR);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.sock", display));
+        let _ = fs::remove_file(&path);
+        let socket = UnixDatagram::bind(&path)?;
+        self.sockets.push(path.clone());
+
+        let tx = self.tx.clone();
+        let display = display.to_string();
+        logging::spawn(move || {
+            let mut buf = [0; 4096];
+            while let Ok(len) = socket.recv(&mut buf) {
+                let state = String::from_utf8_lossy(&buf[..len]).into_owned();
+                if tx.send((display.clone(), state)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        cmd.env(NOTIFY_ENV, &path).env_remove("WATCHDOG_PID");
+        Ok(())
+    }
+
+    pub fn add(&mut self, display: &str, child: Child) {
+        self.instances.push(Instance {
+            display: display.to_string(),
+            child,
+            ready: false,
+            pinged: false,
+            status: None,
+            exited: false,
+        });
+    }
+
+    /// Relay until the compositor goes away
+    pub fn run(mut self) {
+        let socket_path = crate::paths::wayland_socket_path();
+        let check = self.watchdog.map_or(SUPERVISE_INTERVAL, |timeout| timeout / 2);
+        let mut sent_ready = false;
+        let mut sent_status = String::new();
+        let mut next_check = Instant::now() + check;
+
+        loop {
+            match self.rx.recv_timeout(next_check.saturating_duration_since(Instant::now())) {
+                Ok((display, state)) => self.receive(&display, &state),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            let now = Instant::now();
+            if now >= next_check {
+                next_check = now + check;
+                if !socket_path.exists() {
+                    info!("Wayland compositor socket disappeared, parent exiting...");
+                    notify("STOPPING=1");
+                    break;
+                }
+                self.reap();
+                if self.watchdog.is_some() {
+                    self.ping();
+                }
+            }
+
+            let status = self.status();
+            let mut state = Vec::new();
+            if !sent_ready && self.instances.iter().all(|instance| instance.ready || instance.exited) {
+                sent_ready = true;
+                state.push("READY=1".to_string());
+            }
+            if status != sent_status {
+                state.push(format!("STATUS={}", status));
+                sent_status = status;
+            }
+            if !state.is_empty() {
+                notify(&state.join("\n"));
+            }
+        }
+    }
+
+    fn receive(&mut self, display: &str, state: &str) {
+        let Some(instance) = self.instances.iter_mut().find(|instance| instance.display == display) else {
+            return;
+        };
+        let fields: HashMap<&str, &str> = state.lines().filter_map(|line| line.split_once('=')).collect();
+        if fields.get("READY") == Some(&"1") {
+            instance.ready = true;
+        }
+        if fields.get("WATCHDOG") == Some(&"1") {
+            instance.pinged = true;
+        }
+        if let Some(status) = fields.get("STATUS") {
+            instance.status = Some(status.to_string());
+        }
+    }
+
+    /// Notice instances that exited, e.g. because their output is gone
+    fn reap(&mut self) {
+        for instance in self.instances.iter_mut().filter(|instance| !instance.exited) {
+            if let Ok(Some(status)) = instance.child.try_wait() {
+                warn!("Instance for {} exited ({})", instance.display, status);
+                instance.exited = true;
+            }
+        }
+    }
+
+    /// Ping the watchdog if every running instance has since the last time
+    fn ping(&mut self) {
+        let mut running = self.instances.iter_mut().filter(|instance| !instance.exited).peekable();
+        if running.peek().is_none() {
+            return;
+        }
+        if running.all(|instance| instance.pinged) {
+            notify("WATCHDOG=1");
+            for instance in &mut self.instances {
+                instance.pinged = false;
+            }
+        }
+    }
+
+    fn status(&self) -> String {
+        self.instances
+            .iter()
+            .map(|instance| {
+                let status = if instance.exited {
+                    "exited"
+                } else {
+                    instance.status.as_deref().unwrap_or("starting")
+                };
+                format!("{}: {}", instance.display, status)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        for path in &self.sockets {
+            let _ = fs::remove_file(path);
+        }
+        // Only goes if no other relay is using it
+        let _ = fs::remove_dir(runtime_dir().join(RELAY_DIR));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{lock_env, TempDir};
+    use std::thread;
+
+    fn recv(socket: &UnixDatagram) -> String {
+        let mut buf = [0; 4096];
+        let len = socket.recv(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..len]).into_owned()
+    }
+
+    #[test]
+    fn notify_and_relay_over_local_sockets() {
+        let _env = lock_env();
+        let dir = TempDir::new("notify");
+        let systemd = UnixDatagram::bind(dir.path().join("systemd.sock")).unwrap();
+        systemd.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        // SAFETY: the environment lock is held and no other thread reads these
+        unsafe {
+            env::set_var(NOTIFY_ENV, dir.path().join("systemd.sock"));
+            env::set_var("XDG_RUNTIME_DIR", dir.path());
+            env::set_var("WAYLAND_DISPLAY", "no-such-compositor");
+            env::remove_var("WATCHDOG_USEC");
+        }
+
+        notify("STATUS=testing");
+        assert_eq!(recv(&systemd), "STATUS=testing");
+
+        let mut relay = Relay::from_env().unwrap();
+        let mut cmd = Command::new("true");
+        relay.prepare(&mut cmd, "DP-1").unwrap();
+        let child_socket = cmd
+            .get_envs()
+            .find(|(key, _)| *key == NOTIFY_ENV)
+            .and_then(|(_, value)| value)
+            .map(PathBuf::from)
+            .unwrap();
+        assert_eq!(child_socket, dir.path().join(RELAY_DIR).join("DP-1.sock"));
+        relay.add("DP-1", cmd.spawn().unwrap());
+
+        // What a child instance sends once its first frame is up
+        send(child_socket.to_str().unwrap(), "READY=1\nSTATUS=drawing").unwrap();
+        let relaying = thread::spawn(move || relay.run());
+
+        assert_eq!(recv(&systemd), "READY=1\nSTATUS=DP-1: drawing");
+        // The compositor socket doesn't exist, so the relay stops at its first check
+        assert_eq!(recv(&systemd), "STOPPING=1");
+        relaying.join().unwrap();
+        assert!(!child_socket.exists());
+
+        unsafe {
+            env::remove_var(NOTIFY_ENV);
+            env::remove_var("XDG_RUNTIME_DIR");
+            env::remove_var("WAYLAND_DISPLAY");
+        }
+    }
+}
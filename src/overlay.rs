@@ -0,0 +1,78 @@
+use crate::args::MergedConfig;
+use std::collections::BTreeMap;
+
+/// Runtime changes one source wants applied on top of the merged config
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameOverride {
+    pub color: Option<String>,
+    pub opacity: Option<f64>,
+    pub radius: Option<i32>,
+    /// Border widths as (top, right, bottom, left)
+    pub borders: Option<[i32; 4]>,
+    pub hidden: bool,
+}
+
+impl FrameOverride {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, config: &mut MergedConfig) {
+        if let Some(color) = &self.color {
+            config.color = color.clone();
+        }
+        if let Some(opacity) = self.opacity {
+            config.opacity = Some(opacity);
+        }
+        if let Some(radius) = self.radius {
+            config.radius = radius;
+        }
+        if let Some([top, right, bottom, left]) = self.borders {
+            config.top = top;
+            config.right = right;
+            config.bottom = bottom;
+            config.left = left;
+        }
+    }
+}
+
+/// Named override layers kept separate from the on-disk config
+///
+/// Integrations each write their own layer, so a config reload never loses
+/// runtime state and one source can be cleared without touching the others.
+/// Layers are applied in name order; later names win.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeOverlay {
+    layers: BTreeMap<String, FrameOverride>,
+}
+
+impl RuntimeOverlay {
+    /// Replace a source's layer, returning whether anything changed
+    pub fn set(&mut self, source: &str, layer: FrameOverride) -> bool {
+        if layer.is_empty() {
+            return self.clear(source);
+        }
+        if self.layers.get(source) == Some(&layer) {
+            return false;
+        }
+        self.layers.insert(source.to_string(), layer);
+        true
+    }
+
+    /// Drop a source's layer, returning whether it existed
+    pub fn clear(&mut self, source: &str) -> bool {
+        self.layers.remove(source).is_some()
+    }
+
+    pub fn hidden(&self) -> bool {
+        self.layers.values().any(|layer| layer.hidden)
+    }
+
+    pub fn apply(&self, base: &MergedConfig) -> MergedConfig {
+        let mut config = base.clone();
+        for layer in self.layers.values() {
+            layer.apply(&mut config);
+        }
+        config
+    }
+}
@@ -0,0 +1,234 @@
+use crate::colour::Colour;
+use crate::config_set::ConfigSet;
+use crate::wal::{self, WalScheme};
+use std::collections::HashMap;
+
+/// Named colours from the top-level `palette:` block
+///
+/// Anywhere a colour is accepted it may also be a palette name, a `wal:`
+/// reference or one of the derived colour functions:
+///
+/// - `darken(colour, amount)` mixes towards black
+/// - `lighten(colour, amount)` mixes towards white
+/// - `mix(a, b, amount)` mixes `a` towards `b`
+///
+/// Amounts are `0.0..=1.0` or a percentage, and mixing happens in linear light
+/// like every other blend. Palette entries may refer to entries above them.
+#[derive(Debug, Default)]
+pub struct Palette {
+    colours: HashMap<String, Colour>,
+    /// Loaded on first use so configs without `wal:` never touch the cache
+    wal: Option<WalScheme>,
+}
+
+impl Palette {
+    pub fn from_config(config: &ConfigSet) -> Result<Self, String> {
+        let mut palette = Self::default();
+        // A missing block just means an empty palette
+        let Ok(names) = config.get_keys("palette") else {
+            return Ok(palette);
+        };
+
+        for name in names {
+            let key = format!("palette.{}", name);
+            let value: String = config
+                .get(&key)
+                .map_err(|e| format!("{}: expected a colour string ({})", key, e))?;
+            palette.define(&name, &value).map_err(|e| format!("{}: {}", key, e))?;
+        }
+        Ok(palette)
+    }
+
+    /// Evaluate `value` and add it to the palette as `name`
+    pub fn define(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let colour = self.evaluate(value)?;
+        self.colours.insert(name.to_string(), colour);
+        Ok(())
+    }
+
+    /// Resolve a config colour value
+    ///
+    /// Plain colours come back untouched so they can be validated and shown
+    /// as written; anything referring to the palette is evaluated to hex.
+    pub fn resolve(&mut self, value: &str) -> Result<String, String> {
+        if self.is_expression(value.trim()) {
+            Ok(self.evaluate(value)?.to_hex())
+        } else {
+            Ok(value.to_string())
+        }
+    }
+
+    fn is_expression(&self, value: &str) -> bool {
+        self.colours.contains_key(value)
+            || wal::is_reference(value)
+            || ["darken(", "lighten(", "mix("].iter().any(|f| value.starts_with(f))
+    }
+
+    fn evaluate(&mut self, expr: &str) -> Result<Colour, String> {
+        let expr = expr.trim();
+        if let Some(colour) = self.colours.get(expr) {
+            return Ok(*colour);
+        }
+        if wal::is_reference(expr) {
+            if self.wal.is_none() {
+                self.wal = Some(WalScheme::load()?);
+            }
+            let resolved = self.wal.as_ref().unwrap().resolve(expr)?;
+            return Colour::parse(&resolved).map_err(|e| e.to_string());
+        }
+
+        let Some((function, args)) = split_call(expr) else {
+            return Colour::parse(expr).map_err(|e| e.to_string());
+        };
+        let args = split_top_level(args);
+        match (function, args.as_slice()) {
+            ("darken", [colour, amount]) => {
+                let colour = self.evaluate(colour)?;
+                Ok(colour.mix(Colour::BLACK.with_alpha(colour.a), parse_amount(amount)?))
+            }
+            ("lighten", [colour, amount]) => {
+                let colour = self.evaluate(colour)?;
+                Ok(colour.mix(Colour::WHITE.with_alpha(colour.a), parse_amount(amount)?))
+            }
+            ("mix", [a, b, amount]) => {
+                let (a, b) = (self.evaluate(a)?, self.evaluate(b)?);
+                Ok(a.mix(b, parse_amount(amount)?))
+            }
+            ("darken" | "lighten", _) => Err(format!("{}() takes a colour and an amount", function)),
+            ("mix", _) => Err("mix() takes two colours and an amount".to_string()),
+            // rgb(), hsl() and friends
+            _ => Colour::parse(expr).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Whether `value` is a `wal:` reference or a colour function using one
+pub fn mentions_wal(value: &str) -> bool {
+    let value = value.trim();
+    if wal::is_reference(value) {
+        return true;
+    }
+    match split_call(value) {
+        Some(("darken" | "lighten" | "mix", args)) => split_top_level(args).into_iter().any(mentions_wal),
+        _ => false,
+    }
+}
+
+/// Split `name(args)` into its name and argument text
+fn split_call(expr: &str) -> Option<(&str, &str)> {
+    let open = expr.find('(')?;
+    let args = expr[open + 1..].strip_suffix(')')?;
+    Some((expr[..open].trim(), args))
+}
+
+/// Split on commas outside of nested parentheses, so `mix(rgb(1, 2, 3), bg, 50%)`
+/// has three arguments
+fn split_top_level(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+/// Parse `0.2` or `20%`
+fn parse_amount(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    let amount = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+        None => value.parse::<f64>(),
+    }
+    .map_err(|_| format!("'{}' is not an amount, expected e.g. 0.2 or 20%", value))?;
+    if !(0.0..=1.0).contains(&amount) {
+        return Err(format!("amount '{}' is outside 0..1", value));
+    }
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(palette: &mut Palette, value: &str) -> String {
+        palette.resolve(value).unwrap()
+    }
+
+    #[test]
+    fn functions_mix_in_linear_light() {
+        let mut palette = Palette::default();
+        assert_eq!(resolve(&mut palette, "darken(ffffff, 0.5)"), "bcbcbcff");
+        assert_eq!(resolve(&mut palette, "lighten(000000, 0.5)"), "bcbcbcff");
+        assert_eq!(resolve(&mut palette, "mix(ff0000, 0000ff, 0.5)"), "bc00bcff");
+        assert_eq!(resolve(&mut palette, "darken(ff8000, 0)"), "ff8000ff");
+        assert_eq!(resolve(&mut palette, "lighten(ff8000, 1)"), "ffffffff");
+        // Alpha is kept while darkening
+        assert_eq!(resolve(&mut palette, "darken(ffffff80, 100%)"), "00000080");
+    }
+
+    #[test]
+    fn percentages_match_fractions() {
+        let mut palette = Palette::default();
+        for (percent, fraction) in [("20%", "0.2"), ("0%", "0"), ("100 %", "1.0"), ("37.5%", "0.375")] {
+            assert_eq!(
+                resolve(&mut palette, &format!("darken(3b82f6, {})", percent)),
+                resolve(&mut palette, &format!("darken(3b82f6, {})", fraction))
+            );
+        }
+    }
+
+    #[test]
+    fn arguments_split_outside_parentheses() {
+        assert_eq!(split_top_level("rgb(1, 2, 3), mix(a, b, 1), 50%"), ["rgb(1, 2, 3)", "mix(a, b, 1)", "50%"]);
+        assert_eq!(split_top_level(" bg "), ["bg"]);
+        assert_eq!(split_call("mix(rgb(1, 2, 3), bg, 50%)"), Some(("mix", "rgb(1, 2, 3), bg, 50%")));
+        assert_eq!(split_call("161b22"), None);
+
+        let mut palette = Palette::default();
+        assert_eq!(resolve(&mut palette, "mix(rgb(255, 0, 0), darken(ffffff, 100%), 50%)"), "bc0000ff");
+    }
+
+    #[test]
+    fn entries_refer_to_earlier_ones() {
+        let mut palette = Palette::default();
+        palette.define("bg", "161b22").unwrap();
+        palette.define("panel", "lighten(bg, 10%)").unwrap();
+        palette.define("accent", "mix(panel, ff0000, 0.5)").unwrap();
+        let panel = resolve(&mut palette, "lighten(161b22, 10%)");
+        assert_eq!(resolve(&mut palette, "panel"), panel);
+        assert_eq!(resolve(&mut palette, "accent"), resolve(&mut palette, &format!("mix({}, ff0000, 0.5)", panel)));
+        assert_eq!(resolve(&mut palette, "bg"), "161b22ff");
+
+        // Only entries above are known
+        assert!(palette.define("early", "darken(late, 0.1)").is_err());
+        // Plain colours are left as written
+        assert_eq!(resolve(&mut palette, "rgb(1, 2, 3)"), "rgb(1, 2, 3)");
+    }
+
+    #[test]
+    fn bad_amounts_and_calls_are_errors() {
+        let mut palette = Palette::default();
+        assert_eq!(
+            palette.resolve("darken(ffffff, 150%)"),
+            Err("amount '150%' is outside 0..1".to_string())
+        );
+        assert_eq!(palette.resolve("lighten(ffffff, -0.1)"), Err("amount '-0.1' is outside 0..1".to_string()));
+        assert_eq!(
+            palette.resolve("mix(ffffff, 000000, lots)"),
+            Err("'lots' is not an amount, expected e.g. 0.2 or 20%".to_string())
+        );
+        assert_eq!(palette.resolve("darken(ffffff)"), Err("darken() takes a colour and an amount".to_string()));
+        assert_eq!(palette.resolve("mix(ffffff, 0.5)"), Err("mix() takes two colours and an amount".to_string()));
+        assert!(palette.resolve("darken(nonsense, 0.5)").is_err());
+    }
+}
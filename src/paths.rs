@@ -0,0 +1,263 @@
+use crate::config_source::EXTENSIONS;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the config file, below `--config` in precedence
+pub const CONFIG_ENV: &str = "SNUG_CONFIG";
+
+/// Example config shipped by packages, used when nothing else exists
+const EXAMPLE_CONFIG: &str = "/usr/share/doc/snug/snug.rune";
+
+/// Where a config path came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigOrigin {
+    CommandLine,
+    Environment,
+    User,
+    System,
+    Example,
+    /// Nothing exists yet; this is where the user config would go
+    Missing,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CommandLine => write!(f, "--config"),
+            Self::Environment => write!(f, "${}", CONFIG_ENV),
+            Self::User => write!(f, "user config ($XDG_CONFIG_HOME)"),
+            Self::System => write!(f, "system config ($XDG_CONFIG_DIRS)"),
+            Self::Example => write!(f, "packaged example"),
+            Self::Missing => write!(f, "not found, defaults are used"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigLocation {
+    pub path: PathBuf,
+    pub origin: ConfigOrigin,
+}
+
+/// Pick the config file to use
+///
+/// In order: `--config`, `$SNUG_CONFIG`, `$XDG_CONFIG_HOME/snug/snug.rune`,
+/// `snug/snug.rune` in each of `$XDG_CONFIG_DIRS` (and `/etc/xdg`), then the
+/// packaged example. In each directory `snug.rune`, `snug.toml` and
+/// `snug.json` are tried in that order. An explicit path is used even if it
+/// does not exist, so a typo is reported instead of silently loading another
+/// file.
+pub fn locate_config(cli: Option<&Path>) -> ConfigLocation {
+    if let Some(path) = cli {
+        return ConfigLocation {
+            path: expand_tilde(path),
+            origin: ConfigOrigin::CommandLine,
+        };
+    }
+    if let Some(path) = env::var_os(CONFIG_ENV).filter(|p| !p.is_empty()) {
+        return ConfigLocation {
+            path: expand_tilde(Path::new(&path)),
+            origin: ConfigOrigin::Environment,
+        };
+    }
+
+    let user_dir = user_config_dir();
+    let candidates = user_dir
+        .iter()
+        .flat_map(|dir| config_files(dir.clone()).map(|path| (path, ConfigOrigin::User)))
+        .chain(
+            system_config_dirs()
+                .into_iter()
+                .flat_map(|dir| config_files(dir.join("snug")).map(|path| (path, ConfigOrigin::System))),
+        )
+        .chain([(PathBuf::from(EXAMPLE_CONFIG), ConfigOrigin::Example)]);
+    for (path, origin) in candidates {
+        if path.is_file() {
+            return ConfigLocation { path, origin };
+        }
+    }
+
+    ConfigLocation {
+        path: user_dir.map(|dir| dir.join("snug.rune")).unwrap_or_else(|| PathBuf::from("snug.rune")),
+        origin: ConfigOrigin::Missing,
+    }
+}
+
+/// `$XDG_CONFIG_HOME/snug`, falling back to `~/.config/snug`
+fn user_config_dir() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("snug"))
+}
+
+/// `snug.rune`, `snug.toml` and `snug.json` in `dir`, in lookup order
+fn config_files(dir: PathBuf) -> impl Iterator<Item = PathBuf> {
+    EXTENSIONS.iter().map(move |ext| dir.join("snug").with_extension(ext))
+}
+
+/// `$XDG_CONFIG_DIRS` in order, always ending with `/etc/xdg`
+fn system_config_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env::var_os("XDG_CONFIG_DIRS")
+        .map(|value| env::split_paths(&value).filter(|dir| dir.is_absolute()).collect())
+        .unwrap_or_default();
+    let etc = PathBuf::from("/etc/xdg");
+    if !dirs.contains(&etc) {
+        dirs.push(etc);
+    }
+    dirs
+}
+
+/// Expand a leading `~` to the home directory, without requiring UTF-8
+pub fn expand_tilde(path: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~")
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(rest);
+    }
+    path.to_path_buf()
+}
+
+/// `$XDG_RUNTIME_DIR`, or the systemd default for our uid, or the temp dir
+pub fn runtime_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    let per_user = PathBuf::from(format!("/run/user/{}", nix::unistd::getuid()));
+    if per_user.is_dir() {
+        return per_user;
+    }
+    env::temp_dir()
+}
+
+/// Socket of the compositor we are connected to
+///
+/// `$WAYLAND_DISPLAY` may be an absolute path, otherwise it is relative to
+/// the runtime dir.
+pub fn wayland_socket_path() -> PathBuf {
+    let display = env::var_os("WAYLAND_DISPLAY")
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| OsString::from("wayland-0"));
+    let display = PathBuf::from(display);
+    if display.is_absolute() {
+        display
+    } else {
+        runtime_dir().join(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{lock_env, TempDir};
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::sync::MutexGuard;
+
+    const VARS: &[&str] = &["HOME", "XDG_CONFIG_HOME", "XDG_CONFIG_DIRS", CONFIG_ENV];
+
+    /// Holds the env lock and puts the variables back on drop
+    struct Env {
+        saved: Vec<(&'static str, Option<OsString>)>,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Env {
+        fn new() -> Self {
+            let _lock = lock_env();
+            let saved = VARS.iter().map(|&var| (var, env::var_os(var))).collect();
+            Self { saved, _lock }
+        }
+
+        fn set(&self, var: &str, value: impl AsRef<OsStr>) {
+            // SAFETY: the env lock is held
+            unsafe { env::set_var(var, value) }
+        }
+
+        fn remove(&self, var: &str) {
+            // SAFETY: the env lock is held
+            unsafe { env::remove_var(var) }
+        }
+    }
+
+    impl Drop for Env {
+        fn drop(&mut self) {
+            for (var, value) in &self.saved {
+                match value {
+                    Some(value) => self.set(var, value),
+                    None => self.remove(var),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn config_is_picked_in_precedence_order() {
+        let env = Env::new();
+        let dir = TempDir::new("locate");
+        let home = dir.path().join("home");
+        let user = dir.path().join("config");
+        let (first, second) = (dir.path().join("xdg1"), dir.path().join("xdg2"));
+        env.set("HOME", &home);
+        env.set("XDG_CONFIG_HOME", &user);
+        env.set("XDG_CONFIG_DIRS", env::join_paths([&first, &second]).unwrap());
+        env.remove(CONFIG_ENV);
+        let found = |cli: Option<&str>| {
+            let location = locate_config(cli.map(Path::new));
+            (location.path.strip_prefix(dir.path()).unwrap_or(&location.path).to_path_buf(), location.origin)
+        };
+
+        // Only when the machine has no config of its own to fall back to
+        if !Path::new(EXAMPLE_CONFIG).exists() && !Path::new("/etc/xdg/snug").exists() {
+            assert_eq!(found(None), (PathBuf::from("config/snug/snug.rune"), ConfigOrigin::Missing));
+        }
+        dir.write("xdg2/snug/snug.toml", "");
+        assert_eq!(found(None), (PathBuf::from("xdg2/snug/snug.toml"), ConfigOrigin::System));
+        dir.write("xdg1/snug/snug.json", "");
+        assert_eq!(found(None), (PathBuf::from("xdg1/snug/snug.json"), ConfigOrigin::System));
+        dir.write("xdg1/snug/snug.rune", "");
+        assert_eq!(found(None), (PathBuf::from("xdg1/snug/snug.rune"), ConfigOrigin::System));
+        dir.write("config/snug/snug.json", "");
+        assert_eq!(found(None), (PathBuf::from("config/snug/snug.json"), ConfigOrigin::User));
+
+        // An empty variable counts as unset
+        env.set(CONFIG_ENV, "");
+        assert_eq!(found(None).1, ConfigOrigin::User);
+        // Explicit paths win even when they don't exist
+        env.set(CONFIG_ENV, "~/env.rune");
+        assert_eq!(found(None), (PathBuf::from("home/env.rune"), ConfigOrigin::Environment));
+        assert_eq!(found(Some("~/cli.toml")), (PathBuf::from("home/cli.toml"), ConfigOrigin::CommandLine));
+    }
+
+    #[test]
+    fn system_dirs_end_with_etc_xdg() {
+        let env = Env::new();
+        env.set("XDG_CONFIG_DIRS", "/opt/xdg:relative/xdg:/etc/xdg:/usr/xdg");
+        let expected: Vec<PathBuf> = ["/opt/xdg", "/etc/xdg", "/usr/xdg"].iter().map(PathBuf::from).collect();
+        assert_eq!(system_config_dirs(), expected);
+        env.remove("XDG_CONFIG_DIRS");
+        assert_eq!(system_config_dirs(), [PathBuf::from("/etc/xdg")]);
+    }
+
+    #[test]
+    fn tilde_expands_to_home_only_at_the_start() {
+        let env = Env::new();
+        env.set("HOME", "/home/snug");
+        assert_eq!(expand_tilde(Path::new("~")), PathBuf::from("/home/snug"));
+        assert_eq!(expand_tilde(Path::new("~/snug.rune")), PathBuf::from("/home/snug/snug.rune"));
+        assert_eq!(expand_tilde(Path::new("~other/snug.rune")), PathBuf::from("~other/snug.rune"));
+        assert_eq!(expand_tilde(Path::new("/etc/~/snug.rune")), PathBuf::from("/etc/~/snug.rune"));
+        assert_eq!(expand_tilde(Path::new("snug.rune")), PathBuf::from("snug.rune"));
+    }
+
+    #[test]
+    fn non_utf8_paths_survive() {
+        let env = Env::new();
+        env.set("HOME", OsStr::from_bytes(b"/home/sn\xffg"));
+        let expanded = expand_tilde(Path::new(OsStr::from_bytes(b"~/caf\xe9.rune")));
+        assert_eq!(expanded.as_os_str().as_bytes(), b"/home/sn\xffg/caf\xe9.rune");
+
+        env.set(CONFIG_ENV, OsStr::from_bytes(b"/tmp/\xfe.rune"));
+        let location = locate_config(None);
+        assert_eq!((location.path.as_os_str().as_bytes(), location.origin), (&b"/tmp/\xfe.rune"[..], ConfigOrigin::Environment));
+    }
+}
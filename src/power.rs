@@ -0,0 +1,132 @@
+use crate::app::App;
+use tracing::{debug, info, warn};
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::{Connection, Dispatch, QueueHandle, WEnum};
+use wayland_protocols_wlr::output_power_management::v1::client::{
+    zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1,
+    zwlr_output_power_v1::{self, Mode, ZwlrOutputPowerV1},
+};
+
+/// Power state of the bound output, from `zwlr_output_power_manager_v1`
+///
+/// Only watches the mode, never sets it, but wlroots hands out one control per
+/// output and fails any other client asking for it, so tools like wlopm or
+/// swayidle's `output * power off` stop working while snug holds it. Hence
+/// opt-in with `--track-power`. Without it, without the protocol, or when the
+/// compositor refuses a control for the output, the output counts as on and
+/// `is_tracked` is false.
+pub struct OutputPower {
+    manager: Option<ZwlrOutputPowerManagerV1>,
+    control: Option<(WlOutput, ZwlrOutputPowerV1)>,
+    on: bool,
+    /// The output came back on since the main loop last looked
+    restored: bool,
+}
+
+impl Default for OutputPower {
+    fn default() -> Self {
+        Self {
+            manager: None,
+            control: None,
+            on: true,
+            restored: false,
+        }
+    }
+}
+
+impl OutputPower {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<App>, enabled: bool) -> Self {
+        if !enabled {
+            return Self::default();
+        }
+        let manager = globals.bind(qh, 1..=1, ()).ok();
+        if manager.is_none() {
+            debug!("Compositor lacks zwlr_output_power_manager_v1, guessing power state from configures");
+        }
+        Self { manager, ..Self::default() }
+    }
+
+    /// Follow the power mode of `output`, the one the surface is bound to
+    pub fn watch(&mut self, output: Option<&WlOutput>, qh: &QueueHandle<App>) {
+        if self.control.as_ref().map(|(watched, _)| watched) == output {
+            return;
+        }
+        if let Some((_, control)) = self.control.take() {
+            control.destroy();
+        }
+        self.on = true;
+        let (Some(manager), Some(output)) = (&self.manager, output) else { return };
+        // The compositor answers with the current mode right away
+        let control = manager.get_output_power(output, qh, ());
+        self.control = Some((output.clone(), control));
+    }
+
+    /// Whether power changes are reported, rather than guessed
+    pub fn is_tracked(&self) -> bool {
+        self.control.is_some()
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Whether the output came back on since the last call
+    pub fn take_restored(&mut self) -> bool {
+        std::mem::take(&mut self.restored)
+    }
+}
+
+impl Dispatch<ZwlrOutputPowerManagerV1, ()> for App {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrOutputPowerManagerV1,
+        _: <ZwlrOutputPowerManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrOutputPowerV1, ()> for App {
+    fn event(
+        app: &mut Self,
+        control: &ZwlrOutputPowerV1,
+        event: zwlr_output_power_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let power = &mut app.power;
+        // Late events for a control replaced by `watch`
+        if power.control.as_ref().map(|(_, current)| current) != Some(control) {
+            return;
+        }
+        match event {
+            zwlr_output_power_v1::Event::Mode { mode: WEnum::Value(Mode::Off) } => {
+                if power.on {
+                    info!("Output powered off, pausing drawing");
+                }
+                power.on = false;
+            }
+            zwlr_output_power_v1::Event::Mode { mode: WEnum::Value(Mode::On) } => {
+                if !power.on {
+                    info!("Output powered on");
+                    power.restored = true;
+                }
+                power.on = true;
+            }
+            zwlr_output_power_v1::Event::Failed => {
+                // Unsupported output, or another client holds exclusive control
+                warn!("Output power state unavailable, guessing it from configures");
+                control.destroy();
+                power.control = None;
+                // Redraw in case the output was off until now
+                power.restored = !power.on;
+                power.on = true;
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,5 +1,9 @@
 use crate::args::Args;
-use crate::config::{load_config, load_config_or_default};
+use crate::config::{load_config, load_config_or_default, requested_profile};
+use crate::logging;
+use crate::notify;
+use crate::paths;
+use crate::schedule::LocalTime;
 use crate::wayland;
 use crate::event_loop;
 use std::env;
@@ -11,6 +15,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use nix::sys::prctl::set_pdeathsig;
 use nix::sys::signal::Signal;
+use tracing::{error_span, info, warn, Span};
 
 /// Automatically releases the display lock on drop
 pub struct LockGuard {
@@ -33,42 +38,40 @@ impl Drop for LockGuard {
     }
 }
 
-// Helper to expand ~ in paths
-fn expand_tilde(path: &str) -> String {
-    if path.starts_with("~/") {
-        if let Some(home) = dirs::home_dir() {
-            return home.join(&path[2..]).to_string_lossy().to_string();
-        }
-    }
-    path.to_string()
-}
-
 /// Parent process: spawn a child for each configured display
 pub fn spawn_child_processes(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     // Expand tilde in config path ONCE in parent
-    let expanded_config_path = args.config.as_ref().map(|p| expand_tilde(p));
+    let expanded_config_path = args.config.as_deref().map(paths::expand_tilde);
     
     // Load config using the expanded path
-    let snug_config = if let Some(path) = &expanded_config_path {
-        eprintln!("Loading config from: {}", path);
+    let mut snug_config = if let Some(path) = &expanded_config_path {
+        info!("Loading config from: {}", path.display());
         load_config(path)?
     } else {
         load_config_or_default()
     };
     
+    // A profile may add displays, so pick it before deciding which to spawn
+    let profile = requested_profile(&args).or_else(|| {
+        let schedule = snug_config.schedule.as_ref()?;
+        schedule.slot(&LocalTime::now()).profile
+    });
+    snug_config.set_profile(profile.as_deref())?;
+    if let Some(profile) = &profile {
+        info!("Using profile: {}", profile);
+    }
+    
     let exe_path = env::current_exe()?;
     
     let mut spawned = 0;
+    // Under systemd, collect the children's readiness into one notification
+    let mut relay = notify::Relay::from_env();
     
     // Spawn a child process for each configured display
-    for display_name in snug_config.displays.keys() {
-        if display_name == "default" {
-            continue;
-        }
-        
+    for display_name in snug_config.display_names() {
         // Check if instance already running for this display
         if try_acquire_lock(display_name).is_err() {
-            eprintln!("Instance already running for display '{}', skipping", display_name);
+            info!("Instance already running for display '{}', skipping", display_name);
             continue;
         }
         // Release the parent's lock immediately - child will acquire its own
@@ -82,25 +85,52 @@ pub fn spawn_child_processes(args: Args) -> Result<(), Box<dyn std::error::Error
             if let Some(config_path) = &expanded_config_path {
                 cmd.arg("-c").arg(config_path);
             }
+            if let Some(profile) = &args.profile {
+                cmd.arg("--profile").arg(profile);
+            }
+            for _ in 0..args.verbose {
+                cmd.arg("--verbose");
+            }
+            for _ in 0..args.quiet {
+                cmd.arg("--quiet");
+            }
+            if let Some(log_file) = &args.log_file {
+                cmd.arg("--log-file").arg(paths::expand_tilde(log_file));
+            }
+            if args.track_power {
+                cmd.arg("--track-power");
+            }
+            if let Some(relay) = &mut relay {
+                relay.prepare(&mut cmd, display_name)?;
+            }
             
-            cmd.pre_exec(|| {
+            let child = cmd.pre_exec(|| {
                 // Kill child if parent dies
                 set_pdeathsig(Some(Signal::SIGTERM))?;
                 Ok(())
             })
             .spawn()?;
+            if let Some(relay) = &mut relay {
+                relay.add(display_name, child);
+            }
         }
 
         spawned += 1;
     }
     
     if spawned == 0 {
-        eprintln!("No displays configured or all instances already running");
+        warn!("No displays configured or all instances already running");
+        // Don't leave a Type=notify unit waiting for a readiness that never comes
+        notify::notify("READY=1\nSTATUS=No displays to start, all instances already running or none configured");
+        notify::notify("STOPPING=1");
         return Ok(());
     }
     
     // Monitor Wayland compositor instead of sleeping forever
-    wayland::monitor_wayland_compositor();
+    match relay {
+        Some(relay) => relay.run(),
+        None => wayland::monitor_wayland_compositor(),
+    }
     
     Ok(())
 }
@@ -108,12 +138,15 @@ pub fn spawn_child_processes(args: Args) -> Result<(), Box<dyn std::error::Error
 /// Child process: run for a specific display
 pub fn run_child_process(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     let target_display = args.display.as_ref().unwrap();
+    // Everything this process logs is about one display. Error level keeps
+    // the span, and so the field, enabled under any filter that logs at all
+    let _span = error_span!("instance", display = %target_display).entered();
     
     // Try to acquire lock for this display, automatically release on drop
     let _lock_guard = match LockGuard::new(target_display) {
         Some(g) => g,
         None => {
-            eprintln!("Another instance is already running for display '{}'", target_display);
+            warn!("Another instance is already running for display '{}'", target_display);
             return Ok(());
         }
     };
@@ -122,14 +155,16 @@ pub fn run_child_process(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     let r = running.clone();
 
     // Install Ctrl-C / termination handler
+    let span = Span::current();
     ctrlc::set_handler(move || {
-        eprintln!("Received termination signal, shutting down...");
+        let _span = span.enter();
+        info!("Received termination signal, shutting down...");
         r.store(false, Ordering::SeqCst);
     })?;
 
     // Spawn Wayland compositor monitor for child process
     let r2 = running.clone();
-    std::thread::spawn(move || {
+    logging::spawn(move || {
         wayland::monitor_wayland_compositor_with_flag(r2);
     });
 
@@ -140,8 +175,7 @@ pub fn run_child_process(args: Args) -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn get_lock_file_path(display_name: &str) -> std::path::PathBuf {
-    let runtime_dir = env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp".to_string());
-    std::path::PathBuf::from(runtime_dir).join(format!("snug-{}.lock", display_name))
+    paths::runtime_dir().join(format!("snug-{}.lock", display_name))
 }
 
 fn try_acquire_lock(display_name: &str) -> Result<fs::File, std::io::Error> {
@@ -181,4 +215,5 @@ fn try_acquire_lock(display_name: &str) -> Result<fs::File, std::io::Error> {
 pub fn release_lock(display_name: &str) {
     let lock_path = get_lock_file_path(display_name);
     let _ = fs::remove_file(lock_path);
+    let _ = fs::remove_file(crate::ipc::socket_path(display_name));
 }
@@ -0,0 +1,430 @@
+use crate::animation::transition_frame;
+use crate::args::{parse_duration, Args, MergedConfig};
+use crate::config::{DisplayConfig, SnugConfig};
+use crate::config_set::ConfigSet;
+use eyre::{Result, eyre};
+use nix::libc;
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const MINUTES_PER_DAY: f64 = 1440.0;
+
+/// Steps a fade takes, so its colour moves by under 2% at a time
+const FADE_STEPS: u32 = 60;
+
+/// Shortest pause between fade steps, short fades go no faster than this
+const MIN_FADE_STEP: Duration = Duration::from_millis(100);
+
+/// How often to look at the clock otherwise, which also notices suspend and clock changes
+const IDLE_CHECK: Duration = Duration::from_secs(30);
+
+/// When a schedule entry starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum At {
+    /// Minutes after midnight
+    Clock(f64),
+    /// Minutes after (or before, if negative) sunrise
+    Sunrise(f64),
+    Sunset(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    at: At,
+    /// `None` goes back to the base display blocks
+    profile: Option<String>,
+}
+
+/// `schedule:` block: which profile applies at which time of day
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    entries: Vec<Entry>,
+    fade: Duration,
+    /// Latitude and longitude in degrees, needed for sunrise and sunset
+    location: Option<(f64, f64)>,
+}
+
+/// The schedule entry in effect at some moment
+#[derive(Debug, PartialEq)]
+pub struct Slot {
+    /// Which entry, changes exactly when a scheduled time passes
+    pub index: usize,
+    pub profile: Option<String>,
+    /// Profile of the entry before, being faded out of
+    pub previous: Option<String>,
+    /// How far through the fade, 1.0 once it is done
+    pub progress: f64,
+}
+
+impl Schedule {
+    /// Parse the `schedule:` block, `None` when there isn't one
+    pub fn from_config(config: &ConfigSet) -> Result<Option<Self>> {
+        if config.get_keys("schedule").is_err() {
+            return Ok(None);
+        }
+
+        let times: Vec<String> = config
+            .get("schedule.times")
+            .map_err(|_| eyre!("schedule.times: expected a list such as [\"07:00 day\", \"sunset night\"]"))?;
+        let entries = times
+            .iter()
+            .map(|entry| parse_entry(entry))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|e| eyre!("schedule.times: {}", e))?;
+        if entries.is_empty() {
+            return Err(eyre!("schedule.times: needs at least one entry"));
+        }
+
+        let location = match (config.get::<f64>("schedule.latitude").ok(), config.get::<f64>("schedule.longitude").ok()) {
+            (Some(latitude), Some(longitude)) => {
+                if !(-90.0..=90.0).contains(&latitude) {
+                    return Err(eyre!("schedule.latitude: must be between -90 and 90, got {}", latitude));
+                }
+                if !(-180.0..=180.0).contains(&longitude) {
+                    return Err(eyre!("schedule.longitude: must be between -180 and 180, got {}", longitude));
+                }
+                Some((latitude, longitude))
+            }
+            (None, None) => None,
+            _ => return Err(eyre!("schedule: latitude and longitude must be set together")),
+        };
+        if location.is_none() && entries.iter().any(|entry| !matches!(entry.at, At::Clock(_))) {
+            return Err(eyre!("schedule.times: sunrise and sunset need schedule.latitude and schedule.longitude"));
+        }
+
+        let fade = match config.get::<String>("schedule.fade") {
+            Ok(value) => parse_duration(&value).map_err(|e| eyre!("schedule.fade: {}", e))?,
+            Err(_) => Duration::ZERO,
+        };
+
+        Ok(Some(Self { entries, fade, location }))
+    }
+
+    /// How often to recompute the frame during a fade
+    fn fade_step(&self) -> Duration {
+        (self.fade / FADE_STEPS).clamp(MIN_FADE_STEP, IDLE_CHECK)
+    }
+
+    /// Profiles the schedule switches to
+    pub fn profiles(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().filter_map(|entry| entry.profile.as_deref())
+    }
+
+    /// The entry in effect at `now`
+    pub fn slot(&self, now: &LocalTime) -> Slot {
+        let sun = self
+            .location
+            .map(|(latitude, longitude)| sun_times(now.day_of_year, latitude, longitude, now.utc_offset));
+        let mut starts: Vec<(usize, f64)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (index, entry.minute(sun)))
+            .collect();
+        starts.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        // The last entry that already started today, or yesterday's last one
+        let position = starts
+            .iter()
+            .rposition(|(_, start)| *start <= now.minute)
+            .unwrap_or(starts.len() - 1);
+        let (index, start) = starts[position];
+        let (previous, _) = starts[(position + starts.len() - 1) % starts.len()];
+
+        let elapsed = (now.minute - start).rem_euclid(MINUTES_PER_DAY) * 60.0;
+        let progress = if self.fade.is_zero() {
+            1.0
+        } else {
+            (elapsed / self.fade.as_secs_f64()).min(1.0)
+        };
+
+        Slot {
+            index,
+            profile: self.entries[index].profile.clone(),
+            previous: self.entries[previous].profile.clone(),
+            progress,
+        }
+    }
+}
+
+impl Entry {
+    /// Start in minutes after midnight, given today's sunrise and sunset
+    fn minute(&self, sun: Option<(f64, f64)>) -> f64 {
+        // Sun-relative entries are rejected without a location, so the fallback is never used
+        let (sunrise, sunset) = sun.unwrap_or((360.0, 1080.0));
+        match self.at {
+            At::Clock(minute) => minute,
+            At::Sunrise(offset) => (sunrise + offset).rem_euclid(MINUTES_PER_DAY),
+            At::Sunset(offset) => (sunset + offset).rem_euclid(MINUTES_PER_DAY),
+        }
+    }
+}
+
+/// `"<time> [profile]"`
+fn parse_entry(entry: &str) -> Result<Entry, String> {
+    let mut parts = entry.split_whitespace();
+    let time = parts.next().ok_or("empty entry, expected \"<time> [profile]\"")?;
+    let profile = parts.next().map(str::to_string);
+    if parts.next().is_some() {
+        return Err(format!("'{}': expected \"<time> [profile]\"", entry));
+    }
+    let at = parse_time(time).map_err(|e| format!("'{}': {}", entry, e))?;
+    Ok(Entry { at, profile })
+}
+
+/// `HH:MM`, `sunrise`, `sunset`, optionally with an offset like `sunset-30m`
+fn parse_time(time: &str) -> Result<At, String> {
+    if let Some(offset) = time.strip_prefix("sunrise") {
+        return Ok(At::Sunrise(parse_offset(offset)?));
+    }
+    if let Some(offset) = time.strip_prefix("sunset") {
+        return Ok(At::Sunset(parse_offset(offset)?));
+    }
+
+    let invalid = || "expected HH:MM, sunrise or sunset".to_string();
+    let (hours, minutes) = time.split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    Ok(At::Clock((hours * 60 + minutes) as f64))
+}
+
+/// `+1h`, `-30m` or nothing, in minutes
+fn parse_offset(offset: &str) -> Result<f64, String> {
+    if offset.is_empty() {
+        return Ok(0.0);
+    }
+    let (sign, duration) = if let Some(rest) = offset.strip_prefix('+') {
+        (1.0, rest)
+    } else if let Some(rest) = offset.strip_prefix('-') {
+        (-1.0, rest)
+    } else {
+        return Err(format!("expected + or - before the offset, got '{}'", offset));
+    };
+    Ok(sign * parse_duration(duration)?.as_secs_f64() / 60.0)
+}
+
+/// Wall clock time as the schedule sees it
+pub struct LocalTime {
+    /// Minutes after local midnight
+    minute: f64,
+    /// 1 on January 1st
+    day_of_year: u32,
+    /// Minutes ahead of UTC
+    utc_offset: f64,
+}
+
+impl LocalTime {
+    pub fn now() -> Self {
+        // SAFETY: time is given no buffer, localtime_r only writes to the
+        // zeroed tm it is given
+        let tm = unsafe {
+            let now = libc::time(std::ptr::null_mut());
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&now, &mut tm);
+            tm
+        };
+        Self {
+            minute: tm.tm_hour as f64 * 60.0 + tm.tm_min as f64 + tm.tm_sec as f64 / 60.0,
+            day_of_year: tm.tm_yday as u32 + 1,
+            utc_offset: tm.tm_gmtoff as f64 / 60.0,
+        }
+    }
+}
+
+/// Local sunrise and sunset in minutes after midnight, from NOAA's approximation
+///
+/// Good to a minute or two, which is plenty for a frame colour. On days the
+/// sun doesn't rise or set, both land on the same minute.
+fn sun_times(day_of_year: u32, latitude: f64, longitude: f64, utc_offset: f64) -> (f64, f64) {
+    let gamma = 2.0 * PI / 365.0 * (day_of_year as f64 - 1.0);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+
+    // 90.833° allows for refraction and the size of the sun's disc
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = (90.833_f64.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan())
+    .clamp(-1.0, 1.0);
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+
+    let noon = 720.0 - 4.0 * longitude - equation_of_time + utc_offset;
+    (
+        (noon - 4.0 * hour_angle).rem_euclid(MINUTES_PER_DAY),
+        (noon + 4.0 * hour_angle).rem_euclid(MINUTES_PER_DAY),
+    )
+}
+
+/// Applies the schedule in a running instance
+///
+/// A profile picked by hand, with `--profile` or `snug msg profile`, holds
+/// until the next scheduled time passes.
+pub struct ScheduleDriver {
+    slot: Option<usize>,
+    held: bool,
+    next_check: Instant,
+}
+
+impl ScheduleDriver {
+    pub fn new(held: bool) -> Self {
+        Self {
+            slot: None,
+            held,
+            next_check: Instant::now(),
+        }
+    }
+
+    /// Keep a hand-picked profile until the next scheduled time
+    pub fn hold(&mut self) {
+        self.held = true;
+        self.wake();
+    }
+
+    /// Check on the next tick, e.g. after a config reload
+    pub fn wake(&mut self) {
+        self.next_check = Instant::now();
+    }
+
+    /// Display config and frame the schedule wants now, when a check is due
+    pub fn tick(&mut self, config: &mut SnugConfig, display: &str, cli_args: &Args) -> Option<(DisplayConfig, MergedConfig)> {
+        let now = Instant::now();
+        if now < self.next_check {
+            return None;
+        }
+        let Some(schedule) = &config.schedule else {
+            self.next_check = now + IDLE_CHECK;
+            return None;
+        };
+
+        let slot = schedule.slot(&LocalTime::now());
+        let fade_step = schedule.fade_step();
+        if self.slot.is_some_and(|index| index != slot.index) {
+            self.held = false;
+        }
+        self.slot = Some(slot.index);
+
+        let fading = !self.held && slot.progress < 1.0 && slot.previous != slot.profile;
+        if !self.held
+            && let Err(e) = config.set_profile(slot.profile.as_deref())
+        {
+            warn!("Schedule: {}", e);
+        }
+
+        let display_config = config.get_display_config(display);
+        let mut frame = cli_args.merge_with_config(&display_config);
+        if fading {
+            let from = cli_args.merge_with_config(&config.display_config_for(slot.previous.as_deref(), display));
+            transition_frame(&from, &frame, slot.progress).apply(&mut frame);
+        }
+
+        self.next_check = now + if fading { fade_step } else { IDLE_CHECK };
+        Some((display_config, frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(times: &[&str], fade: Duration) -> Schedule {
+        Schedule {
+            entries: times.iter().map(|entry| parse_entry(entry).unwrap()).collect(),
+            fade,
+            location: None,
+        }
+    }
+
+    fn at(hours: u32, minutes: u32) -> LocalTime {
+        LocalTime {
+            minute: (hours * 60 + minutes) as f64,
+            day_of_year: 1,
+            utc_offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn times_parse() {
+        assert_eq!(parse_time("00:00"), Ok(At::Clock(0.0)));
+        assert_eq!(parse_time("07:30"), Ok(At::Clock(450.0)));
+        assert_eq!(parse_time("23:59"), Ok(At::Clock(1439.0)));
+        assert_eq!(parse_time("sunrise"), Ok(At::Sunrise(0.0)));
+        assert_eq!(parse_time("sunset-30m"), Ok(At::Sunset(-30.0)));
+        assert_eq!(parse_time("sunrise+1h"), Ok(At::Sunrise(60.0)));
+        for bad in ["24:00", "12:60", "7", "noon", "12:xx", "sunset30m"] {
+            assert!(parse_time(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn offsets_parse() {
+        assert_eq!(parse_offset(""), Ok(0.0));
+        assert_eq!(parse_offset("+1h"), Ok(60.0));
+        assert_eq!(parse_offset("-45m"), Ok(-45.0));
+        assert_eq!(parse_offset("+90s"), Ok(1.5));
+        assert!(parse_offset("1h").is_err());
+        assert!(parse_offset("+soon").is_err());
+    }
+
+    #[test]
+    fn slot_wraps_past_midnight() {
+        let schedule = schedule(&["06:00 day", "23:30 night"], Duration::from_secs(3600));
+
+        let slot = schedule.slot(&at(0, 15));
+        assert_eq!(slot.profile.as_deref(), Some("night"));
+        assert_eq!(slot.previous.as_deref(), Some("day"));
+        assert_eq!(slot.progress, 0.75);
+
+        // Before the first entry of the day, yesterday's last one still applies
+        let slot = schedule.slot(&at(5, 59));
+        assert_eq!((slot.index, slot.progress), (1, 1.0));
+
+        let slot = schedule.slot(&at(6, 30));
+        assert_eq!(slot.profile.as_deref(), Some("day"));
+        assert_eq!(slot.previous.as_deref(), Some("night"));
+        assert_eq!(slot.progress, 0.5);
+    }
+
+    #[test]
+    fn entry_without_profile_returns_to_the_base_blocks() {
+        let schedule = schedule(&["08:00 work", "18:00"], Duration::ZERO);
+        let slot = schedule.slot(&at(19, 0));
+        assert_eq!((slot.profile, slot.previous, slot.progress), (None, Some("work".to_string()), 1.0));
+    }
+
+    #[test]
+    fn fade_steps_follow_the_fade() {
+        assert_eq!(schedule(&["06:00"], Duration::from_secs(600)).fade_step(), Duration::from_secs(10));
+        assert_eq!(schedule(&["06:00"], Duration::from_secs(2)).fade_step(), MIN_FADE_STEP);
+        assert_eq!(schedule(&["06:00"], Duration::from_secs(7200)).fade_step(), IDLE_CHECK);
+    }
+
+    #[test]
+    fn sun_times_match_known_days() {
+        let close = |(sunrise, sunset): (f64, f64), expected: (f64, f64)| {
+            assert!(
+                (sunrise - expected.0).abs() < 5.0 && (sunset - expected.1).abs() < 5.0,
+                "got {:?}, expected {:?}",
+                (sunrise, sunset),
+                expected
+            );
+        };
+        // London on the summer solstice, BST: 04:43 and 21:21
+        close(sun_times(172, 51.5, -0.13, 60.0), (283.0, 1281.0));
+        // The equator at the March equinox, UTC: 06:04 and 18:11
+        close(sun_times(80, 0.0, 0.0, 0.0), (364.0, 1091.0));
+        // Midnight sun: both land on the same minute
+        let (sunrise, sunset) = sun_times(172, 80.0, 0.0, 0.0);
+        assert!((sunrise - sunset).abs() < 1e-6, "{} {}", sunrise, sunset);
+    }
+}
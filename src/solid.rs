@@ -0,0 +1,229 @@
+use crate::app::App;
+use crate::args::MergedConfig;
+use crate::colour::Colour;
+use smithay_client_toolkit::compositor::CompositorState;
+use smithay_client_toolkit::subcompositor::SubcompositorState;
+use tracing::debug;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::protocol::wl_subsurface::WlSubsurface;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::{Connection, Dispatch, QueueHandle};
+use wayland_protocols::wp::single_pixel_buffer::v1::client::wp_single_pixel_buffer_manager_v1::WpSinglePixelBufferManagerV1;
+use wayland_protocols::wp::viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter};
+
+/// Globals the fast path needs, all or nothing
+struct Globals {
+    subcompositor: SubcompositorState,
+    viewporter: WpViewporter,
+    pixels: WpSinglePixelBufferManagerV1,
+    qh: QueueHandle<App>,
+}
+
+/// Square frames as four scaled single-pixel buffers, without SHM
+///
+/// The layer surface shows a transparent pixel stretched to the output, and
+/// each border is a subsurface showing the frame colour stretched to the
+/// border's size. Without `wp_single_pixel_buffer_manager_v1` and
+/// `wp_viewporter`, `fits` is always false and frames go through SHM.
+#[derive(Default)]
+pub struct SolidFrame {
+    globals: Option<Globals>,
+    edges: Option<Edges>,
+    /// Frame colour as premultiplied RGBA and the buffer holding it
+    colour: Option<([u32; 4], WlBuffer)>,
+}
+
+/// Per-surface state, tied to one layer surface
+struct Edges {
+    parent: WlSurface,
+    viewport: WpViewport,
+    clear: WlBuffer,
+    /// Top, bottom, left, right
+    edges: Vec<Edge>,
+    /// Whether the last commit on `parent` went through this path
+    shown: bool,
+}
+
+struct Edge {
+    surface: WlSurface,
+    subsurface: WlSubsurface,
+    viewport: WpViewport,
+}
+
+impl SolidFrame {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<App>, compositor: &CompositorState) -> Self {
+        let bound = (|| {
+            Some(Globals {
+                subcompositor: SubcompositorState::bind(compositor.wl_compositor().clone(), globals, qh).ok()?,
+                viewporter: globals.bind(qh, 1..=1, ()).ok()?,
+                pixels: globals.bind(qh, 1..=1, ()).ok()?,
+                qh: qh.clone(),
+            })
+        })();
+        if bound.is_none() {
+            debug!("Compositor lacks wp_single_pixel_buffer_manager_v1 or wp_viewporter, drawing square frames into SHM buffers");
+        }
+        Self { globals: bound, ..Self::default() }
+    }
+
+    /// Whether `config` can be drawn without SHM
+    ///
+    /// Rounded corners and shadows need real pixels.
+    pub fn fits(&self, config: &MergedConfig) -> bool {
+        self.globals.is_some() && config.radius <= 0 && !config.shadow_enabled.unwrap_or(false)
+    }
+
+    /// Show the frame on `parent` and commit it
+    ///
+    /// `parent` is the layer surface; its input region is left to the caller.
+    pub fn draw(&mut self, parent: &WlSurface, width: i32, height: i32, config: &MergedConfig, colour: Colour, hidden: bool) {
+        if self.edges.as_ref().is_some_and(|edges| &edges.parent != parent) {
+            self.forget_surface();
+        }
+        let Some(globals) = &self.globals else { return };
+        let edges = self.edges.get_or_insert_with(|| Edges::new(globals, parent));
+
+        let (r, g, b, a) = colour.premultiplied();
+        let rgba = [r, g, b, a].map(|v| (v.clamp(0.0, 1.0) * u32::MAX as f64).round() as u32);
+        let stale = match &self.colour {
+            Some((current, _)) if *current == rgba => None,
+            _ => {
+                let buffer = globals.pixels.create_u32_rgba_buffer(rgba[0], rgba[1], rgba[2], rgba[3], &globals.qh, ());
+                self.colour.replace((rgba, buffer)).map(|(_, old)| old)
+            }
+        };
+        let Some((_, buffer)) = &self.colour else { return };
+
+        let (top, bottom, left, right) = (config.top, config.bottom, config.left, config.right);
+        let middle = height - top - bottom;
+        let rects = [
+            (0, 0, width, top),
+            (0, height - bottom, width, bottom),
+            (0, top, left, middle),
+            (width - right, top, right, middle),
+        ];
+        for (edge, (x, y, w, h)) in edges.edges.iter().zip(rects) {
+            if hidden || w <= 0 || h <= 0 {
+                edge.surface.attach(None, 0, 0);
+            } else {
+                edge.subsurface.set_position(x, y);
+                edge.viewport.set_destination(w, h);
+                edge.surface.attach(Some(buffer), 0, 0);
+                edge.surface.damage(0, 0, w, h);
+            }
+            // Synchronized, so this only takes effect with the parent's commit
+            edge.surface.commit();
+        }
+
+        edges.viewport.set_destination(width, height);
+        parent.attach(Some(&edges.clear), 0, 0);
+        parent.damage(0, 0, width, height);
+        parent.commit();
+        edges.shown = true;
+
+        // Replaced on screen by the commit above
+        if let Some(old) = stale {
+            old.destroy();
+        }
+    }
+
+    /// Take the frame down before the layer surface gets an SHM buffer
+    ///
+    /// Takes effect with the caller's next commit on the layer surface.
+    pub fn hide(&mut self) {
+        let Some(edges) = self.edges.as_mut().filter(|edges| edges.shown) else { return };
+        for edge in &edges.edges {
+            edge.surface.attach(None, 0, 0);
+            edge.surface.commit();
+        }
+        // Back to the buffer's own size
+        edges.viewport.set_destination(-1, -1);
+        edges.shown = false;
+    }
+
+    /// Drop the subsurfaces, e.g. before the layer surface is recreated
+    pub fn forget_surface(&mut self) {
+        if let Some(edges) = self.edges.take() {
+            edges.destroy();
+        }
+    }
+}
+
+impl Edges {
+    fn new(globals: &Globals, parent: &WlSurface) -> Self {
+        let edges = (0..4)
+            .map(|_| {
+                let (subsurface, surface) = globals.subcompositor.create_subsurface(parent.clone(), &globals.qh);
+                let viewport = globals.viewporter.get_viewport(&surface, &globals.qh, ());
+                Edge { surface, subsurface, viewport }
+            })
+            .collect();
+        Self {
+            parent: parent.clone(),
+            viewport: globals.viewporter.get_viewport(parent, &globals.qh, ()),
+            clear: globals.pixels.create_u32_rgba_buffer(0, 0, 0, 0, &globals.qh, ()),
+            edges,
+            shown: false,
+        }
+    }
+
+    fn destroy(self) {
+        for edge in self.edges {
+            edge.viewport.destroy();
+            edge.subsurface.destroy();
+            edge.surface.destroy();
+        }
+        self.viewport.destroy();
+        self.clear.destroy();
+    }
+}
+
+impl Dispatch<WpViewporter, ()> for App {
+    fn event(
+        _: &mut Self,
+        _: &WpViewporter,
+        _: <WpViewporter as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WpViewport, ()> for App {
+    fn event(
+        _: &mut Self,
+        _: &WpViewport,
+        _: <WpViewport as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WpSinglePixelBufferManagerV1, ()> for App {
+    fn event(
+        _: &mut Self,
+        _: &WpSinglePixelBufferManagerV1,
+        _: <WpSinglePixelBufferManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+/// Single-pixel buffers never change, so `release` needs no bookkeeping
+impl Dispatch<WlBuffer, ()> for App {
+    fn event(
+        _: &mut Self,
+        _: &WlBuffer,
+        _: <WlBuffer as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
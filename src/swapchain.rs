@@ -0,0 +1,59 @@
+use smithay_client_toolkit::shm::slot::{Buffer, CreateBufferError, SlotPool};
+use smithay_client_toolkit::shm::{CreatePoolError, Shm};
+use wayland_client::protocol::wl_shm;
+
+/// One buffer on screen, one to draw the next frame into
+const BUFFERS: usize = 2;
+
+/// Double-buffered SHM storage for a surface
+///
+/// A buffer is only drawn into again once the compositor has sent
+/// `wl_buffer.release`, so a frame never changes while it's being read.
+/// Buffers are reused until the size changes; the pool outlives them.
+pub struct Swapchain {
+    pool: SlotPool,
+    buffers: Vec<Buffer>,
+    size: (i32, i32),
+}
+
+impl Swapchain {
+    pub fn new(shm: &Shm) -> Result<Self, CreatePoolError> {
+        Ok(Self {
+            // Grows on demand to fit two frames of the output
+            pool: SlotPool::new(2 * 1024 * 1024, shm)?,
+            buffers: Vec::new(),
+            size: (0, 0),
+        })
+    }
+
+    /// A released `width`x`height` ARGB buffer and its pixels
+    ///
+    /// `None` while the compositor still holds both buffers; try again after
+    /// the next dispatch.
+    pub fn acquire(&mut self, width: i32, height: i32) -> Result<Option<(&Buffer, &mut [u8])>, CreateBufferError> {
+        if self.size != (width, height) {
+            // Buffers still on screen are destroyed once released
+            self.buffers.clear();
+            self.size = (width, height);
+        }
+
+        let index = match self.buffers.iter().position(|buffer| !buffer.slot().has_active_buffers()) {
+            Some(index) => index,
+            None if self.buffers.len() < BUFFERS => {
+                let (buffer, _) = self.pool.create_buffer(width, height, width * 4, wl_shm::Format::Argb8888)?;
+                self.buffers.push(buffer);
+                self.buffers.len() - 1
+            }
+            None => return Ok(None),
+        };
+        let buffer = &self.buffers[index];
+        Ok(buffer.canvas(&mut self.pool).map(|canvas| (buffer, canvas)))
+    }
+
+    /// Start over with fresh buffers, e.g. for a new surface
+    ///
+    /// The old surface may never release what it was showing.
+    pub fn reset(&mut self) {
+        self.buffers.clear();
+    }
+}
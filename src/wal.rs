@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefix marking a colour as a reference into the generated colour scheme
+pub const PREFIX: &str = "wal:";
+
+/// Colour schemes written by pywal and wallust, in lookup order
+pub fn scheme_paths() -> Vec<PathBuf> {
+    let Some(cache) = dirs::cache_dir() else {
+        return Vec::new();
+    };
+    vec![
+        cache.join("wal").join("colors.json"),
+        cache.join("wallust").join("colors.json"),
+    ]
+}
+
+/// The first colour scheme that exists on disk
+pub fn find_scheme() -> Option<PathBuf> {
+    scheme_paths().into_iter().find(|path| path.exists())
+}
+
+/// Colours from a pywal-style `colors.json`
+///
+/// Both `colors.colorN` and `special.{background,foreground,cursor}` are
+/// flattened into one map, so `wal:color4` and `wal:background` both resolve.
+#[derive(Debug, Clone, Default)]
+pub struct WalScheme {
+    path: PathBuf,
+    colours: HashMap<String, String>,
+}
+
+impl WalScheme {
+    pub fn load() -> Result<Self, String> {
+        let path = find_scheme().ok_or_else(|| {
+            let searched: Vec<String> = scheme_paths().iter().map(|p| p.display().to_string()).collect();
+            format!("no colour scheme found (looked in {})", searched.join(", "))
+        })?;
+        Self::from_path(&path)
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let json: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+
+        let mut colours = HashMap::new();
+        for section in ["colors", "special"] {
+            let Some(entries) = json.get(section).and_then(|v| v.as_object()) else {
+                continue;
+            };
+            for (name, value) in entries {
+                if let Some(colour) = value.as_str() {
+                    colours.insert(name.clone(), colour.to_string());
+                }
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            colours,
+        })
+    }
+
+    /// Resolve a `wal:name` reference, leaving any other value untouched
+    pub fn resolve(&self, value: &str) -> Result<String, String> {
+        let Some(name) = value.trim().strip_prefix(PREFIX) else {
+            return Ok(value.to_string());
+        };
+        self.colours
+            .get(name.trim())
+            .cloned()
+            .ok_or_else(|| format!("'{}' is not defined in {}", name.trim(), self.path.display()))
+    }
+}
+
+/// Whether a config value refers to the colour scheme
+pub fn is_reference(value: &str) -> bool {
+    value.trim().starts_with(PREFIX)
+}
@@ -0,0 +1,200 @@
+use crate::config_set::ConfigSet;
+use crate::logging;
+use notify::{Event, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// How long to wait for an editor to finish its write / rename dance
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Everything a reload depends on
+///
+/// Files are never watched directly: editors that save by renaming a new
+/// file over the old one leave an inode watch pointing at the deleted file.
+/// Their directories are watched instead, and events are filtered by path.
+#[derive(Debug, PartialEq)]
+struct WatchPlan {
+    files: Vec<PathBuf>,
+    conf_dir: PathBuf,
+}
+
+impl WatchPlan {
+    fn new(config_path: &Path) -> Self {
+        let (mut files, conf_dir, wal) = match ConfigSet::load(config_path) {
+            Ok(set) => (
+                set.files().map(|(path, _)| path.to_path_buf()).collect(),
+                set.conf_dir().to_path_buf(),
+                crate::config::uses_wal(&set),
+            ),
+            // Missing or broken: keep watching the main file until it's fixed
+            Err(_) => (
+                vec![config_path.to_path_buf()],
+                config_path.parent().unwrap_or(Path::new(".")).join("conf.d"),
+                false,
+            ),
+        };
+        // Recolour when pywal / wallust regenerates the scheme `wal:` colours refer to,
+        // but leave the cache directory alone when nothing does
+        if wal {
+            files.extend(crate::wal::scheme_paths());
+        }
+        Self { files, conf_dir }
+    }
+
+    /// Directories to watch: the nearest existing one for every path we care about
+    fn directories(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        let parents = self.files.iter().filter_map(|file| file.parent());
+        for dir in parents.chain([self.conf_dir.as_path()]) {
+            if let Some(existing) = existing_ancestor(dir)
+                && !dirs.contains(&existing)
+            {
+                dirs.push(existing);
+            }
+        }
+        dirs
+    }
+
+    /// Whether an event on `path` can change what a reload would load
+    fn is_relevant(&self, path: &Path) -> bool {
+        self.files.iter().any(|file| file == path)
+            || (path.parent() == Some(self.conf_dir.as_path()) && crate::config_source::is_config_file(path))
+            // A directory on the way to a watched file appeared or went away
+            || self.files.iter().chain([&self.conf_dir]).any(|file| file.starts_with(path) && file != path)
+            || path == self.conf_dir
+    }
+}
+
+fn existing_ancestor(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find(|ancestor| ancestor.is_dir()).map(Path::to_path_buf)
+}
+
+/// Set up file watcher for config and colour scheme hot reload
+///
+/// Reacts to writes, creates, renames and removes, and keeps working when
+/// the config is deleted and recreated or does not exist yet at startup.
+pub fn setup_config_watcher(
+    config_needs_reload: Arc<Mutex<bool>>,
+    running: Arc<AtomicBool>,
+    custom_config_path: Option<PathBuf>,
+) {
+    logging::spawn(move || {
+        let config_path = crate::paths::locate_config(custom_config_path.as_deref()).path;
+
+        if config_path.exists() {
+            debug!("Watching config file: {}", config_path.display());
+        } else {
+            info!("Config file does not exist yet, waiting for it: {}", config_path.display());
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+            if let Ok(event) = res {
+                let _ = tx.send(event);
+            }
+        }) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                warn!("Failed to create file watcher, hot reload disabled: {}", e);
+                return;
+            }
+        };
+
+        let mut plan = WatchPlan::new(&config_path);
+        let mut watched = Vec::new();
+        sync_watches(&mut watcher, &mut watched, plan.directories());
+
+        while running.load(Ordering::SeqCst) {
+            let event = match rx.recv_timeout(Duration::from_secs(1)) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if !is_change(&event) || !event.paths.iter().any(|path| plan.is_relevant(path)) {
+                continue;
+            }
+
+            // Debounce: editors often remove, create and write in quick succession
+            thread::sleep(DEBOUNCE);
+            while rx.try_recv().is_ok() {}
+
+            // Includes may have changed and directories may have appeared
+            plan = WatchPlan::new(&config_path);
+            sync_watches(&mut watcher, &mut watched, plan.directories());
+
+            // Mid-rename or deleted: wait for the file to come back
+            if !config_path.exists() {
+                warn!("Config file removed, waiting for it to reappear: {}", config_path.display());
+                continue;
+            }
+
+            info!("Config or colour scheme changed, reloading...");
+            *config_needs_reload.lock().unwrap() = true;
+        }
+    });
+}
+
+fn is_change(event: &Event) -> bool {
+    // Renames are reported as modify(name) events
+    event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove()
+}
+
+/// Watch exactly `wanted`, dropping watches that are no longer needed
+fn sync_watches(watcher: &mut impl Watcher, watched: &mut Vec<PathBuf>, wanted: Vec<PathBuf>) {
+    watched.retain(|path| {
+        if wanted.contains(path) {
+            return true;
+        }
+        let _ = watcher.unwatch(path);
+        false
+    });
+    for path in wanted {
+        if watched.contains(&path) {
+            continue;
+        }
+        match watcher.watch(&path, RecursiveMode::NonRecursive) {
+            Ok(()) => watched.push(path),
+            Err(e) => warn!("Could not watch {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{lock_env, TempDir};
+
+    #[test]
+    fn scheme_is_only_watched_when_referenced() {
+        // The scheme paths depend on $HOME
+        let _env = lock_env();
+        let dir = TempDir::new("watch");
+        let watches_scheme = |contents: &str| {
+            let config = dir.write("snug.rune", contents);
+            let plan = WatchPlan::new(&config);
+            assert_eq!(plan.files[0], config);
+            match &plan.files[1..] {
+                [] => false,
+                scheme => scheme == crate::wal::scheme_paths(),
+            }
+        };
+
+        assert!(!watches_scheme("DP-1:\n  radius = 0\n  color = \"ff0000\"\nend\n"));
+        // Mentions outside of colours don't count
+        assert!(!watches_scheme(
+            "# try wal: colours some day\nDP-1:\n  radius = 0\n  source = \"echo wal:color1\"\nend\n"
+        ));
+
+        assert!(watches_scheme("DP-1:\n  radius = 0\n  color = \"wal:color4\"\nend\n"));
+        assert!(watches_scheme("DP-1:\n  radius = 0\n  workspace_colors = [\"special:magic:wal:color3\"]\nend\n"));
+        assert!(watches_scheme("palette:\n  accent = \"darken(wal:color1, 10%)\"\nend\n"));
+        assert!(watches_scheme(
+            "DP-1:\n  radius = 0\nend\n\nprofiles:\n  night:\n    DP-1:\n      color = \"mix(000000, wal:color0, 0.5)\"\n    end\n  end\nend\n"
+        ));
+    }
+}
@@ -1,25 +1,37 @@
 use crate::app::App;
+use crate::args::Args;
+use crate::config::DisplayConfig;
+use crate::overlay::RuntimeOverlay;
+use crate::power::OutputPower;
+use crate::swapchain::Swapchain;
 use crate::process::release_lock;
 use smithay_client_toolkit::{
+    compositor::CompositorState,
+    output::OutputState,
+    registry::RegistryState,
+    seat::SeatState,
+    shm::Shm,
+    shell::wlr_layer::LayerShell,
     shell::{wlr_layer::{Anchor, KeyboardInteractivity, Layer}, WaylandSurface},
-    shm::{slot::SlotPool},
 };
-use wayland_client::{Connection, protocol::wl_output};
-use std::env;
+use wayland_client::{Connection, globals::registry_queue_init, protocol::wl_output};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
+use tracing::{debug, info};
+use crate::solid::SolidFrame;
 
 /// Monitor Wayland compositor socket - exit when it disappears (for parent process)
 pub fn monitor_wayland_compositor() {
-    let socket_path = get_wayland_socket_path();
+    let socket_path = crate::paths::wayland_socket_path();
     
     loop {
         thread::sleep(Duration::from_secs(2));
         
-        if !std::path::Path::new(&socket_path).exists() {
-            eprintln!("Wayland compositor socket disappeared, parent exiting...");
+        if !socket_path.exists() {
+            info!("Wayland compositor socket disappeared, parent exiting...");
             std::process::exit(0);
         }
     }
@@ -27,26 +39,19 @@ pub fn monitor_wayland_compositor() {
 
 /// Monitor Wayland compositor socket with flag (for child processes)
 pub fn monitor_wayland_compositor_with_flag(running: Arc<AtomicBool>) {
-    let socket_path = get_wayland_socket_path();
+    let socket_path = crate::paths::wayland_socket_path();
     
     loop {
         thread::sleep(Duration::from_secs(2));
         
-        if !std::path::Path::new(&socket_path).exists() {
-            eprintln!("Wayland compositor socket disappeared, shutting down...");
+        if !socket_path.exists() {
+            info!("Wayland compositor socket disappeared, shutting down...");
             running.store(false, Ordering::SeqCst);
             break;
         }
     }
 }
 
-/// Get the Wayland socket path
-pub fn get_wayland_socket_path() -> String {
-    let wayland_display = env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "wayland-0".to_string());
-    let xdg_runtime = env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/run/user/1000".to_string());
-    format!("{}/{}", xdg_runtime, wayland_display)
-}
-
 /// Create a Wayland connection with retry logic
 pub fn create_wayland_connection(target_display: &str) -> Result<Connection, wayland_client::ConnectError> {
     let mut retries = 0;
@@ -75,7 +80,7 @@ pub fn find_target_output(
         if let Some(info) = temp_app.output_state.info(&output) {
             if let Some(name) = info.name.as_ref() {
                 if name == target_display || (target_display == "default" && temp_app.bound_output.is_none()) {
-                    eprintln!("Found target output: {}", name);
+                    debug!("Found target output: {}", name);
                     if target_display != "default" {
                         return Some(output.clone()); // Exact match found
                     }
@@ -88,13 +93,57 @@ pub fn find_target_output(
     None
 }
 
+/// Logical size of every connected output by name, `None` without a compositor
+pub fn output_sizes() -> Option<HashMap<String, (i32, i32)>> {
+    let conn = Connection::connect_to_env().ok()?;
+    let (globals, mut event_queue) = registry_queue_init(&conn).ok()?;
+    let qh = event_queue.handle();
+    
+    let mut app = App {
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        seat_state: SeatState::new(&globals, &qh),
+        compositor_state: CompositorState::bind(&globals, &qh).ok()?,
+        layer_shell: LayerShell::bind(&globals, &qh).ok()?,
+        shm: Shm::bind(&globals, &qh).ok()?,
+        swapchain: None,
+        layer: None,
+        width: 0,
+        height: 0,
+        config: Args::default().merge_with_config(&DisplayConfig::default()),
+        overlay: RuntimeOverlay::default(),
+        animation: None,
+        bound_output: None,
+        target_display_name: String::new(),
+        needs_recreation: false,
+        power: OutputPower::default(),
+        solid: SolidFrame::default(),
+        drawn: None,
+        draw_pending: false,
+    };
+    
+    // The second roundtrip picks up xdg_output's logical sizes
+    event_queue.roundtrip(&mut app).ok()?;
+    event_queue.roundtrip(&mut app).ok()?;
+    
+    Some(
+        app.output_state
+            .outputs()
+            .filter_map(|output| {
+                let info = app.output_state.info(&output)?;
+                Some((info.name?, info.logical_size?))
+            })
+            .collect(),
+    )
+}
+
 /// Create the initial layer surface and app setup
 pub fn setup_layer_surface(
     temp_app: &mut App,
     target_output: Option<wl_output::WlOutput>,
     qh: &wayland_client::QueueHandle<App>,
-) -> Result<(SlotPool, smithay_client_toolkit::shell::wlr_layer::LayerSurface), Box<dyn std::error::Error>> {
-    let pool = SlotPool::new(2 * 1024 * 1024, &temp_app.shm)?;
+) -> Result<(Swapchain, smithay_client_toolkit::shell::wlr_layer::LayerSurface), Box<dyn std::error::Error>> {
+    let swapchain = Swapchain::new(&temp_app.shm)?;
     let surface = temp_app.compositor_state.create_surface(qh);
     
     // Bind to specific output
@@ -112,5 +161,5 @@ pub fn setup_layer_surface(
     layer.set_keyboard_interactivity(KeyboardInteractivity::None);
     layer.commit();
     
-    Ok((pool, layer))
+    Ok((swapchain, layer))
 }